@@ -1,74 +1,332 @@
+use std::rc::Rc;
+
 use crate::{
-    expr::{Expr, LiteralType},
-    visitor::Visitor,
+    expr::{Ast, ExprId, LiteralType},
+    printer::Printer,
+    stmt::{FunctionDecl, Stmt},
+    token::Token,
+    visitor::{StmtVisitor, Visitor},
 };
 
 pub struct AstPrinter {}
 
 impl AstPrinter {
-    pub fn print(&self, expr: Box<Expr>) -> String {
-        self.visit(expr)
-    }
-    fn parenthesize(&self, name: String, exprs: Vec<Box<Expr>>) -> String {
+    fn parenthesize(&mut self, ast: &Ast, name: &str, exprs: Vec<ExprId>) -> String {
         let mut str = String::from("(");
 
-        str.push_str(&name);
+        str.push_str(name);
 
         for e in exprs {
             str.push(' ');
-            str.push_str(&self.visit(e));
+            str.push_str(&self.visit_expr(ast, e));
+        }
+
+        str.push(')');
+
+        str
+    }
+}
+
+impl Printer for AstPrinter {
+    fn print(&mut self, ast: &Ast, expr: ExprId) -> String {
+        self.visit_expr(ast, expr)
+    }
+
+    fn print_program(&mut self, ast: &Ast, statements: &[Stmt]) -> String {
+        statements
+            .iter()
+            .map(|statement| self.visit_stmt(ast, statement))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl StmtVisitor<String> for AstPrinter {
+    fn visit_block_stmt(&mut self, ast: &Ast, statements: &[Stmt]) -> String {
+        let mut str = String::from("(block");
+
+        for statement in statements {
+            str.push(' ');
+            str.push_str(&self.visit_stmt(ast, statement));
+        }
+
+        str.push(')');
+
+        str
+    }
+
+    fn visit_expression_stmt(&mut self, ast: &Ast, expr: ExprId) -> String {
+        self.parenthesize(ast, ";", vec![expr])
+    }
+
+    fn visit_function_stmt(&mut self, ast: &Ast, declaration: &Rc<FunctionDecl>) -> String {
+        let params: Vec<&str> = declaration.params.iter().map(|p| p.text.as_str()).collect();
+        let mut str = format!("(fun {} ({})", declaration.name.text, params.join(" "));
+
+        for statement in &declaration.body {
+            str.push(' ');
+            str.push_str(&self.visit_stmt(ast, statement));
         }
 
         str.push(')');
 
         str
     }
+
+    fn visit_if_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> String {
+        let mut str = format!(
+            "(if {} {}",
+            self.visit_expr(ast, condition),
+            self.visit_stmt(ast, then_branch)
+        );
+
+        if let Some(else_branch) = else_branch {
+            str.push(' ');
+            str.push_str(&self.visit_stmt(ast, else_branch));
+        }
+
+        str.push(')');
+
+        str
+    }
+
+    fn visit_print_stmt(&mut self, ast: &Ast, expr: ExprId) -> String {
+        self.parenthesize(ast, "print", vec![expr])
+    }
+
+    fn visit_return_stmt(&mut self, ast: &Ast, _keyword: &Token, value: Option<ExprId>) -> String {
+        self.parenthesize(ast, "return", value.into_iter().collect())
+    }
+
+    fn visit_var_stmt(&mut self, ast: &Ast, name: &Token, initializer: Option<ExprId>) -> String {
+        let name = format!("var {}", name.text);
+        self.parenthesize(ast, &name, initializer.into_iter().collect())
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        ast: &Ast,
+        _keyword: &Token,
+        condition: ExprId,
+        body: &Stmt,
+    ) -> String {
+        format!(
+            "(while {} {})",
+            self.visit_expr(ast, condition),
+            self.visit_stmt(ast, body)
+        )
+    }
 }
 
 impl Visitor<String> for AstPrinter {
-    fn visit(&self, expr: Box<Expr>) -> String {
-        match *expr {
-            Expr::Binary {
-                left,
-                operator,
-                right,
-            } => self.parenthesize(operator.literal, vec![left, right]),
-            Expr::Grouping(e) => self.parenthesize(String::from("group"), vec![e]),
-            Expr::Literal(lit) => match lit {
-                LiteralType::Number(n) => n.to_string(),
-                LiteralType::String(s) => s,
-                LiteralType::True(t) => t.to_string(),
-                LiteralType::False(f) => f.to_string(),
-                LiteralType::Nil(()) => String::from("nil"),
-            },
-            Expr::Unary { operator, right } => self.parenthesize(operator.literal, vec![right]),
+    fn visit_assign(&mut self, ast: &Ast, _id: ExprId, name: &Token, value: ExprId) -> String {
+        let name = format!("= {}", name.text);
+        self.parenthesize(ast, &name, vec![value])
+    }
+
+    fn visit_binary(&mut self, ast: &Ast, left: ExprId, operator: &Token, right: ExprId) -> String {
+        self.parenthesize(ast, &operator.text, vec![left, right])
+    }
+
+    fn visit_call(
+        &mut self,
+        ast: &Ast,
+        callee: ExprId,
+        _paren: &Token,
+        arguments: &[ExprId],
+    ) -> String {
+        let mut exprs = vec![callee];
+        exprs.extend_from_slice(arguments);
+        self.parenthesize(ast, "call", exprs)
+    }
+
+    fn visit_comma(&mut self, ast: &Ast, left: ExprId, right: ExprId) -> String {
+        self.parenthesize(ast, ",", vec![left, right])
+    }
+
+    fn visit_grouping(&mut self, ast: &Ast, inner: ExprId) -> String {
+        self.parenthesize(ast, "group", vec![inner])
+    }
+
+    fn visit_literal(&mut self, _ast: &Ast, literal: &LiteralType) -> String {
+        match literal {
+            LiteralType::Number(n) => n.to_string(),
+            LiteralType::String(s) => s.clone(),
+            LiteralType::True(t) => t.to_string(),
+            LiteralType::False(f) => f.to_string(),
+            LiteralType::Nil(()) => String::from("nil"),
         }
     }
+
+    fn visit_logical(
+        &mut self,
+        ast: &Ast,
+        left: ExprId,
+        operator: &Token,
+        right: ExprId,
+    ) -> String {
+        self.parenthesize(ast, &operator.text, vec![left, right])
+    }
+
+    fn visit_ternary(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    ) -> String {
+        self.parenthesize(ast, "?:", vec![condition, then_branch, else_branch])
+    }
+
+    fn visit_unary(&mut self, ast: &Ast, operator: &Token, right: ExprId) -> String {
+        self.parenthesize(ast, &operator.text, vec![right])
+    }
+
+    fn visit_variable(&mut self, _ast: &Ast, _id: ExprId, name: &Token) -> String {
+        name.text.clone()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{expr::Expr, token::Token};
-    use crate::{expr::LiteralType, token::TokenType};
+    use crate::parser::Parser;
+    use crate::printer::Printer;
+    use crate::scanner::Scanner;
+    use crate::{
+        expr::LiteralType,
+        token::{Span, TokenType},
+    };
+    use crate::{
+        expr::{Ast, Expr},
+        token::Token,
+    };
 
     use super::AstPrinter;
 
     #[test]
     fn it_pretty_prints() {
-        let expr = Box::new(Expr::Binary {
-            left: Box::new(Expr::Unary {
-                operator: Token::new(TokenType::Minus, String::from("-"), String::from("-"), 1),
-                right: Box::new(Expr::Literal(LiteralType::Number(123.0))),
-            }),
-            operator: Token::new(TokenType::Star, String::from("*"), String::from("*"), 1),
-            right: Box::new(Expr::Grouping(Box::from(Expr::Literal(
-                LiteralType::Number(45.67),
-            )))),
+        let mut ast = Ast::new();
+        let number = ast.add(Expr::Literal(LiteralType::Number(123.0)));
+        let left = ast.add(Expr::Unary {
+            operator: Token::new(
+                TokenType::Minus,
+                String::from("-"),
+                String::from("-"),
+                1,
+                Span::default(),
+            ),
+            right: number,
+        });
+        let number = ast.add(Expr::Literal(LiteralType::Number(45.67)));
+        let right = ast.add(Expr::Grouping(number));
+        let expr = ast.add(Expr::Binary {
+            left,
+            operator: Token::new(
+                TokenType::Star,
+                String::from("*"),
+                String::from("*"),
+                1,
+                Span::default(),
+            ),
+            right,
         });
 
-        let ast_printer = AstPrinter {};
-        let output = ast_printer.print(expr);
+        let mut ast_printer = AstPrinter {};
+        let output = ast_printer.print(&ast, expr);
 
         assert_eq!(output, String::from("(* (- 123) (group 45.67))"));
+        // The tree is borrowed rather than consumed, so it can be walked again.
+        assert_eq!(ast_printer.print(&ast, expr), output);
+    }
+
+    fn print_source(source: &str) -> String {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let expr = Parser::new(tokens, &mut ast)
+            .parse_expression()
+            .expect("source should parse");
+
+        AstPrinter {}.print(&ast, expr)
+    }
+
+    #[test]
+    fn it_prints_parsed_source() {
+        assert_eq!(print_source("-123 * (45.67)"), "(* (- 123) (group 45.67))");
+        assert_eq!(print_source("1 + 2 * 3 == 7"), "(== (+ 1 (* 2 3)) 7)");
+    }
+
+    #[test]
+    fn it_prints_ternary_expressions() {
+        assert_eq!(print_source("true ? 1 : 2"), "(?: true 1 2)");
+        // The conditional operator is right-associative.
+        assert_eq!(
+            print_source("1 == 2 ? \"one\" : 2 == 2 ? \"two\" : \"many\""),
+            "(?: (== 1 2) one (?: (== 2 2) two many))"
+        );
+    }
+
+    #[test]
+    fn it_prints_comma_expressions() {
+        // The comma operator is left-associative and binds looser than the conditional operator.
+        assert_eq!(print_source("1, 2, 3"), "(, (, 1 2) 3)");
+        assert_eq!(print_source("1, nil ? 2 : 3"), "(, 1 (?: nil 2 3))");
+        assert_eq!(print_source("nil ? 1, 2 : 3"), "(?: nil (, 1 2) 3)");
+    }
+
+    #[test]
+    fn it_prints_calls() {
+        assert_eq!(
+            print_source("f(1, 2 + 3)(x)"),
+            "(call (call f 1 (+ 2 3)) x)"
+        );
+        assert_eq!(print_source("-f()"), "(- (call f))");
+    }
+
+    #[test]
+    fn it_prints_statements() {
+        let mut scanner = Scanner::new("var a = 1; { var b; print a = b = 2; } a;");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        assert_eq!(
+            AstPrinter {}.print_program(&ast, &statements),
+            "(var a 1)\n(block (var b) (print (= a (= b 2))))\n(; a)"
+        );
+    }
+
+    #[test]
+    fn it_prints_desugared_for_loops() {
+        let mut scanner =
+            Scanner::new("for (var i = 0; i < 2; i = i + 1) if (a or b and c) print i; else x;");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        assert_eq!(
+            AstPrinter {}.print_program(&ast, &statements),
+            "(block (var i 0) (while (< i 2) \
+             (block (if (or a (and b c)) (print i) (; x)) (; (= i (+ i 1))))))"
+        );
+    }
+
+    #[test]
+    fn it_prints_function_declarations() {
+        let mut scanner = Scanner::new("fun add(a, b) { return a + b; } fun f() { return; }");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        assert_eq!(
+            AstPrinter {}.print_program(&ast, &statements),
+            "(fun add (a b) (return (+ a b)))\n(fun f () (return))"
+        );
     }
 }
@@ -0,0 +1,121 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::ptr;
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::stmt::FunctionDecl;
+use crate::value::Value;
+
+/// How many arguments a function takes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Fixed(usize),
+    /// Any number of arguments, which the function checks itself.
+    Variadic,
+}
+
+/// What a native function returns: its result, or why it has none.
+pub type NativeResult = Result<Value, NativeError>;
+
+/// Why a native function didn't return a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NativeError {
+    /// The message of the runtime error to raise at the call.
+    Message(String),
+    /// Stop the script, handing the host this exit status, as `exit` does.
+    Exit(i32),
+}
+
+// Lets natives fail with just a message, whether returned or passed on with `?`.
+impl From<String> for NativeError {
+    fn from(message: String) -> NativeError {
+        NativeError::Message(message)
+    }
+}
+
+type NativeFn = dyn Fn(&[Value]) -> NativeResult;
+
+/**
+A function implemented in Rust, like `clock` in
+[10.2 Native Functions](https://craftinginterpreters.com/functions.html#native-functions).
+
+Natives don't know where they were called from, so rather than a `RuntimeError` they fail with a
+`NativeError`; the interpreter turns it into an error pointing at the call.
+*/
+pub struct NativeFunction {
+    pub name: String,
+    pub arity: Arity,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, arity: Arity, function: F) -> NativeFunction
+    where
+        F: Fn(&[Value]) -> NativeResult + 'static,
+    {
+        NativeFunction {
+            name: String::from(name),
+            arity,
+            function: Box::new(function),
+        }
+    }
+
+    pub fn call(&self, arguments: &[Value]) -> NativeResult {
+        (self.function)(arguments)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+// Functions are only ever equal to themselves.
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &NativeFunction) -> bool {
+        ptr::eq(self, other)
+    }
+}
+
+/**
+A function declared in Lox, following [10.4 Function Objects](https://craftinginterpreters.com/functions.html#function-objects).
+
+The function keeps hold of the environment it was declared in, so it can still see the variables
+around it when it is called after they have gone out of scope; see
+[10.6 Local Functions and Closures](https://craftinginterpreters.com/functions.html#local-functions-and-closures).
+*/
+pub struct LoxFunction {
+    pub declaration: Rc<FunctionDecl>,
+    pub closure: Rc<RefCell<Environment>>,
+}
+
+impl LoxFunction {
+    pub fn name(&self) -> &str {
+        &self.declaration.name.text
+    }
+
+    pub fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+}
+
+// The closure isn't printed since it may well contain the function itself.
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoxFunction")
+            .field("name", &self.name())
+            .field("arity", &self.arity())
+            .finish()
+    }
+}
+
+impl PartialEq for LoxFunction {
+    fn eq(&self, other: &LoxFunction) -> bool {
+        ptr::eq(self, other)
+    }
+}
@@ -0,0 +1,268 @@
+use crate::expr::{Ast, Expr, ExprId, LiteralType};
+use crate::fold::Folder;
+use crate::stmt::Stmt;
+use crate::token::{Token, TokenType};
+
+/**
+Evaluates operators whose operands are all literals once, ahead of time, so `(1 + 2) * 3` becomes
+`9`.
+
+Only folds that can't change the program's behaviour are made: an operation that would raise a
+runtime error, such as `-"x"` or `1 + "a"`, is left in the tree so the error is still reported when
+the expression is evaluated.
+*/
+pub struct ConstantFolder {}
+
+impl ConstantFolder {
+    pub fn fold(&mut self, ast: &mut Ast, expr: ExprId) -> ExprId {
+        self.fold_expr(ast, expr)
+    }
+
+    pub fn fold_program(&mut self, ast: &mut Ast, statements: Vec<Stmt>) -> Vec<Stmt> {
+        statements
+            .into_iter()
+            .map(|statement| self.fold_stmt(ast, statement))
+            .collect()
+    }
+}
+
+impl Folder for ConstantFolder {
+    fn fold_binary(
+        &mut self,
+        ast: &mut Ast,
+        left: ExprId,
+        operator: Token,
+        right: ExprId,
+    ) -> ExprId {
+        let left = self.fold_expr(ast, left);
+        let right = self.fold_expr(ast, right);
+
+        if let (Expr::Literal(l), Expr::Literal(r)) = (&ast[left], &ast[right]) {
+            if let Some(value) = fold_binary_literals(l, &operator, r) {
+                return ast.add(Expr::Literal(value));
+            }
+        }
+
+        ast.add(Expr::Binary {
+            left,
+            operator,
+            right,
+        })
+    }
+
+    fn fold_comma(&mut self, ast: &mut Ast, left: ExprId, right: ExprId) -> ExprId {
+        let left = self.fold_expr(ast, left);
+        let right = self.fold_expr(ast, right);
+
+        // A literal on the left has no side effects, so only the right operand matters.
+        if let Expr::Literal(_) = ast[left] {
+            return right;
+        }
+
+        ast.add(Expr::Comma { left, right })
+    }
+
+    fn fold_grouping(&mut self, ast: &mut Ast, inner: ExprId) -> ExprId {
+        let inner = self.fold_expr(ast, inner);
+
+        if let Expr::Literal(_) = ast[inner] {
+            return inner;
+        }
+
+        ast.add(Expr::Grouping(inner))
+    }
+
+    fn fold_logical(
+        &mut self,
+        ast: &mut Ast,
+        left: ExprId,
+        operator: Token,
+        right: ExprId,
+    ) -> ExprId {
+        let left = self.fold_expr(ast, left);
+        let right = self.fold_expr(ast, right);
+
+        // A literal on the left decides up front whether the right operand is evaluated at all.
+        if let Expr::Literal(literal) = &ast[left] {
+            let short_circuits = match operator.token_type {
+                TokenType::Or => is_truthy(literal),
+                _ => !is_truthy(literal),
+            };
+            return if short_circuits { left } else { right };
+        }
+
+        ast.add(Expr::Logical {
+            left,
+            operator,
+            right,
+        })
+    }
+
+    fn fold_ternary(
+        &mut self,
+        ast: &mut Ast,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    ) -> ExprId {
+        let condition = self.fold_expr(ast, condition);
+        let then_branch = self.fold_expr(ast, then_branch);
+        let else_branch = self.fold_expr(ast, else_branch);
+
+        if let Expr::Literal(literal) = &ast[condition] {
+            return if is_truthy(literal) {
+                then_branch
+            } else {
+                else_branch
+            };
+        }
+
+        ast.add(Expr::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn fold_unary(&mut self, ast: &mut Ast, operator: Token, right: ExprId) -> ExprId {
+        let right = self.fold_expr(ast, right);
+
+        if let Expr::Literal(literal) = &ast[right] {
+            let folded = match (&operator.token_type, literal) {
+                (TokenType::Bang, literal) => Some(boolean(!is_truthy(literal))),
+                (TokenType::Minus, LiteralType::Number(n)) => Some(LiteralType::Number(-n)),
+                _ => None,
+            };
+            if let Some(value) = folded {
+                return ast.add(Expr::Literal(value));
+            }
+        }
+
+        ast.add(Expr::Unary { operator, right })
+    }
+}
+
+fn fold_binary_literals(
+    left: &LiteralType,
+    operator: &Token,
+    right: &LiteralType,
+) -> Option<LiteralType> {
+    match (left, &operator.token_type, right) {
+        (_, TokenType::EqualEqual, _) => Some(boolean(is_equal(left, right))),
+        (_, TokenType::BangEqual, _) => Some(boolean(!is_equal(left, right))),
+        (LiteralType::String(l), TokenType::Plus, LiteralType::String(r)) => {
+            Some(LiteralType::String(format!("{l}{r}")))
+        }
+        (LiteralType::Number(l), token_type, LiteralType::Number(r)) => match token_type {
+            TokenType::Plus => Some(LiteralType::Number(l + r)),
+            TokenType::Minus => Some(LiteralType::Number(l - r)),
+            TokenType::Star => Some(LiteralType::Number(l * r)),
+            TokenType::Slash => Some(LiteralType::Number(l / r)),
+            TokenType::Greater => Some(boolean(l > r)),
+            TokenType::GreaterEqual => Some(boolean(l >= r)),
+            TokenType::Less => Some(boolean(l < r)),
+            TokenType::LessEqual => Some(boolean(l <= r)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// `false` and `nil` are falsey and everything else is truthy; see
+// [7.2.4 Truthiness and falsiness](https://craftinginterpreters.com/evaluating-expressions.html#truthiness-and-falsiness).
+fn is_truthy(literal: &LiteralType) -> bool {
+    !matches!(literal, LiteralType::False(_) | LiteralType::Nil(()))
+}
+
+fn is_equal(left: &LiteralType, right: &LiteralType) -> bool {
+    match (left, right) {
+        (LiteralType::Number(l), LiteralType::Number(r)) => l == r,
+        (LiteralType::String(l), LiteralType::String(r)) => l == r,
+        (LiteralType::True(_), LiteralType::True(_)) => true,
+        (LiteralType::False(_), LiteralType::False(_)) => true,
+        (LiteralType::Nil(()), LiteralType::Nil(())) => true,
+        _ => false,
+    }
+}
+
+fn boolean(value: bool) -> LiteralType {
+    if value {
+        LiteralType::True(true)
+    } else {
+        LiteralType::False(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConstantFolder;
+    use crate::ast_printer::AstPrinter;
+    use crate::expr::Ast;
+    use crate::parser::Parser;
+    use crate::printer::Printer;
+    use crate::scanner::Scanner;
+
+    fn fold(source: &str) -> String {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let expr = Parser::new(tokens, &mut ast).parse_expression().unwrap();
+
+        let folded = ConstantFolder {}.fold(&mut ast, expr);
+
+        AstPrinter {}.print(&ast, folded)
+    }
+
+    #[test]
+    fn it_folds_arithmetic_and_comparisons() {
+        assert_eq!(fold("(1 + 2) * 3"), "9");
+        assert_eq!(fold("10 / 4 - 1"), "1.5");
+        assert_eq!(fold("1 + 2 >= 3"), "true");
+        assert_eq!(fold("1 == \"1\""), "false");
+        assert_eq!(fold("nil != nil"), "false");
+    }
+
+    #[test]
+    fn it_folds_unary_operators_and_concatenation() {
+        assert_eq!(fold("-(2 * 3)"), "-6");
+        assert_eq!(fold("!nil"), "true");
+        assert_eq!(fold("!!\"a\""), "true");
+        assert_eq!(fold("\"a\" + \"b\""), "ab");
+    }
+
+    #[test]
+    fn it_folds_conditionals_and_commas() {
+        assert_eq!(fold("1 > 2 ? \"yes\" : \"no\""), "no");
+        assert_eq!(fold("1, 2 + 3"), "5");
+    }
+
+    #[test]
+    fn it_short_circuits_literal_logical_operands() {
+        assert_eq!(fold("nil or \"a\""), "a");
+        assert_eq!(fold("false and x"), "false");
+        assert_eq!(fold("1 and x"), "x");
+        assert_eq!(fold("x or 1"), "(or x 1)");
+    }
+
+    #[test]
+    fn it_folds_the_expressions_in_statements() {
+        let mut scanner = Scanner::new("var a = 1 + 2; { print a * (2 - 1); }");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        let folded = ConstantFolder {}.fold_program(&mut ast, statements);
+
+        assert_eq!(
+            AstPrinter {}.print_program(&ast, &folded),
+            "(var a 3)\n(block (print (* a 1)))"
+        );
+    }
+
+    #[test]
+    fn it_preserves_runtime_errors() {
+        assert_eq!(fold("-\"x\""), "(- x)");
+        assert_eq!(fold("(1 + 1) + \"a\""), "(+ 2 a)");
+        assert_eq!(fold("true < false"), "(< true false)");
+    }
+}
@@ -0,0 +1,392 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::callable::{Arity, NativeError, NativeFunction, NativeResult};
+use crate::value::Value;
+
+/// Why a `Value` couldn't be converted to the Rust type that was asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    /// What the Rust type needs, e.g. "a number" or "an integer from 0 to 255".
+    pub expected: String,
+    /// What the value was instead, e.g. "string" or "1.5".
+    pub found: String,
+}
+
+impl ConversionError {
+    fn new(expected: &str, value: &Value) -> ConversionError {
+        ConversionError {
+            expected: String::from(expected),
+            found: String::from(value.type_name()),
+        }
+    }
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Expected {} but got {}.", self.expected, self.found)
+    }
+}
+
+impl Error for ConversionError {}
+
+// Lets natives use `?` on conversions, failing the call with the conversion's message.
+impl From<ConversionError> for String {
+    fn from(error: ConversionError) -> String {
+        error.to_string()
+    }
+}
+
+impl From<ConversionError> for NativeError {
+    fn from(error: ConversionError) -> NativeError {
+        NativeError::Message(error.to_string())
+    }
+}
+
+/**
+Converts a Lox `Value` to a Rust type, failing with a `ConversionError` if the value has the wrong
+type. Conversions borrow from the value where they can, so `&str` can be read out of a string
+without copying it.
+
+```
+use rlox::convert::FromLox;
+use rlox::Lox;
+
+let mut lox = Lox::new();
+let value = lox.eval("1 + 2").unwrap();
+assert_eq!(u8::from_lox(&value), Ok(3));
+assert!(String::from_lox(&value).is_err());
+```
+*/
+pub trait FromLox<'a>: Sized {
+    fn from_lox(value: &'a Value) -> Result<Self, ConversionError>;
+}
+
+/// Converts a Rust value to a Lox `Value`. This can't fail, though large integers may lose
+/// precision since every Lox number is an `f64`.
+pub trait IntoLox {
+    fn into_lox(self) -> Value;
+}
+
+impl FromLox<'_> for Value {
+    fn from_lox(value: &Value) -> Result<Value, ConversionError> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoLox for Value {
+    fn into_lox(self) -> Value {
+        self
+    }
+}
+
+impl FromLox<'_> for () {
+    fn from_lox(value: &Value) -> Result<(), ConversionError> {
+        match value {
+            Value::Nil => Ok(()),
+            other => Err(ConversionError::new("nil", other)),
+        }
+    }
+}
+
+impl IntoLox for () {
+    fn into_lox(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl FromLox<'_> for f64 {
+    fn from_lox(value: &Value) -> Result<f64, ConversionError> {
+        match value {
+            Value::Number(n) => Ok(*n),
+            other => Err(ConversionError::new("a number", other)),
+        }
+    }
+}
+
+impl IntoLox for f64 {
+    fn into_lox(self) -> Value {
+        Value::Number(self)
+    }
+}
+
+impl FromLox<'_> for bool {
+    fn from_lox(value: &Value) -> Result<bool, ConversionError> {
+        match value {
+            Value::Boolean(b) => Ok(*b),
+            other => Err(ConversionError::new("a boolean", other)),
+        }
+    }
+}
+
+impl IntoLox for bool {
+    fn into_lox(self) -> Value {
+        Value::Boolean(self)
+    }
+}
+
+impl<'a> FromLox<'a> for &'a str {
+    fn from_lox(value: &'a Value) -> Result<&'a str, ConversionError> {
+        match value {
+            Value::String(s) => Ok(s),
+            other => Err(ConversionError::new("a string", other)),
+        }
+    }
+}
+
+impl IntoLox for &str {
+    fn into_lox(self) -> Value {
+        Value::String(String::from(self))
+    }
+}
+
+impl FromLox<'_> for String {
+    fn from_lox(value: &Value) -> Result<String, ConversionError> {
+        <&str>::from_lox(value).map(String::from)
+    }
+}
+
+impl IntoLox for String {
+    fn into_lox(self) -> Value {
+        Value::String(self)
+    }
+}
+
+// Lox only has `f64` numbers, so an integer is a number with no fractional part that fits in the
+// integer type. Numbers are checked against `MAX + 1`, a power of two and so exact as an `f64`,
+// since the 64-bit `MAX`es round up to it and a check against them would let it through.
+macro_rules! integer_conversions {
+    ($($t:ty),*) => {
+        $(
+            impl FromLox<'_> for $t {
+                fn from_lox(value: &Value) -> Result<$t, ConversionError> {
+                    let n = f64::from_lox(value).map_err(|_| {
+                        ConversionError::new("an integer", value)
+                    })?;
+
+                    let limit = <$t>::MAX as f64 + 1.0;
+                    if n.fract() != 0.0 || n < <$t>::MIN as f64 || n >= limit {
+                        return Err(ConversionError {
+                            expected: format!(
+                                "an integer from {} to {}",
+                                <$t>::MIN,
+                                <$t>::MAX
+                            ),
+                            found: n.to_string(),
+                        });
+                    }
+
+                    Ok(n as $t)
+                }
+            }
+
+            impl IntoLox for $t {
+                fn into_lox(self) -> Value {
+                    Value::Number(self as f64)
+                }
+            }
+        )*
+    };
+}
+
+integer_conversions!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// `nil` is `None` and anything else is converted to `T`.
+impl<'a, T: FromLox<'a>> FromLox<'a> for Option<T> {
+    fn from_lox(value: &'a Value) -> Result<Option<T>, ConversionError> {
+        match value {
+            Value::Nil => Ok(None),
+            other => T::from_lox(other).map(Some),
+        }
+    }
+}
+
+impl<T: IntoLox> IntoLox for Option<T> {
+    fn into_lox(self) -> Value {
+        self.map_or(Value::Nil, IntoLox::into_lox)
+    }
+}
+
+impl<'a, T: FromLox<'a>> FromLox<'a> for Vec<T> {
+    fn from_lox(value: &'a Value) -> Result<Vec<T>, ConversionError> {
+        match value {
+            Value::List(items) => items.iter().map(T::from_lox).collect(),
+            other => Err(ConversionError::new("a list", other)),
+        }
+    }
+}
+
+impl<T: IntoLox> IntoLox for Vec<T> {
+    fn into_lox(self) -> Value {
+        Value::List(Rc::new(self.into_iter().map(IntoLox::into_lox).collect()))
+    }
+}
+
+impl<'a, T: FromLox<'a>> FromLox<'a> for HashMap<String, T> {
+    fn from_lox(value: &'a Value) -> Result<HashMap<String, T>, ConversionError> {
+        match value {
+            Value::Map(entries) => entries
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), T::from_lox(value)?)))
+                .collect(),
+            other => Err(ConversionError::new("a map", other)),
+        }
+    }
+}
+
+impl<T: IntoLox> IntoLox for HashMap<String, T> {
+    fn into_lox(self) -> Value {
+        let entries: BTreeMap<String, Value> = self
+            .into_iter()
+            .map(|(key, value)| (key, value.into_lox()))
+            .collect();
+        Value::Map(Rc::new(entries))
+    }
+}
+
+/// What a typed native function can return: any `IntoLox` value, or a `Result` of one whose `Err`
+/// is the message of the runtime error to raise.
+pub trait IntoNativeResult {
+    fn into_native_result(self) -> NativeResult;
+}
+
+impl<T: IntoLox> IntoNativeResult for T {
+    fn into_native_result(self) -> NativeResult {
+        Ok(self.into_lox())
+    }
+}
+
+impl<T: IntoLox> IntoNativeResult for Result<T, String> {
+    fn into_native_result(self) -> NativeResult {
+        self.map(IntoLox::into_lox).map_err(NativeError::Message)
+    }
+}
+
+/**
+A Rust closure whose parameters and result convert to and from Lox values, so it can be registered
+with `Lox::define_function` without handling `Value`s by hand. `Args` is the tuple of the closure's
+parameter types; it only exists to tell the implementations for different numbers of parameters
+apart.
+*/
+pub trait IntoNative<Args> {
+    fn into_native(self, name: &str) -> NativeFunction;
+}
+
+macro_rules! typed_natives {
+    ($($count:literal => ($($arg:ident: $index:tt),*)),*) => {
+        $(
+            impl<F, R, $($arg),*> IntoNative<($($arg,)*)> for F
+            where
+                F: Fn($($arg),*) -> R + 'static,
+                R: IntoNativeResult,
+                $($arg: for<'a> FromLox<'a>,)*
+            {
+                #[allow(unused_variables)]
+                fn into_native(self, name: &str) -> NativeFunction {
+                    NativeFunction::new(name, Arity::Fixed($count), move |arguments| {
+                        self($($arg::from_lox(&arguments[$index])?),*).into_native_result()
+                    })
+                }
+            }
+        )*
+    };
+}
+
+typed_natives!(
+    0 => (),
+    1 => (A: 0),
+    2 => (A: 0, B: 1),
+    3 => (A: 0, B: 1, C: 2),
+    4 => (A: 0, B: 1, C: 2, D: 3)
+);
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{ConversionError, FromLox, IntoLox};
+    use crate::engine::Lox;
+    use crate::value::Value;
+
+    #[test]
+    fn it_round_trips_rust_values() {
+        assert_eq!(f64::from_lox(&1.5.into_lox()), Ok(1.5));
+        assert_eq!(i32::from_lox(&(-7).into_lox()), Ok(-7));
+        assert_eq!(bool::from_lox(&true.into_lox()), Ok(true));
+        assert_eq!(String::from_lox(&"lox".into_lox()), Ok(String::from("lox")));
+        assert_eq!(Option::<f64>::from_lox(&None::<f64>.into_lox()), Ok(None));
+
+        let list = vec![Some(1u8), None].into_lox();
+        assert_eq!(list.to_string(), "[1, nil]");
+        assert_eq!(Vec::<Option<u8>>::from_lox(&list), Ok(vec![Some(1), None]));
+
+        let map = HashMap::from([(String::from("b"), "x"), (String::from("a"), "y")]).into_lox();
+        assert_eq!(map.to_string(), "{\"a\": \"y\", \"b\": \"x\"}");
+        assert_eq!(HashMap::<String, String>::from_lox(&map).unwrap()["a"], "y");
+    }
+
+    #[test]
+    fn it_passes_maps_to_and_from_natives() {
+        let mut lox = Lox::new();
+        lox.define_function("scores", || {
+            HashMap::from([(String::from("a"), 1.0), (String::from("b"), 2.0)])
+        });
+        lox.define_function("total", |scores: HashMap<String, f64>| {
+            scores.values().sum::<f64>()
+        });
+
+        assert_eq!(lox.eval("total(scores())").unwrap(), Value::Number(3.0));
+        assert_eq!(
+            lox.eval("scores()").unwrap().to_string(),
+            "{\"a\": 1, \"b\": 2}"
+        );
+        assert_eq!(lox.eval("len(scores())").unwrap(), Value::Number(2.0));
+        assert_eq!(
+            lox.eval("type(scores())").unwrap(),
+            Value::String(String::from("map"))
+        );
+        assert!(lox.eval("total(split(\"a\", \"\"))").is_err());
+    }
+
+    #[test]
+    fn it_describes_failed_conversions() {
+        let error = |result: Result<u8, ConversionError>| result.unwrap_err().to_string();
+
+        assert_eq!(
+            error(u8::from_lox(&Value::String(String::from("1")))),
+            "Expected an integer but got string."
+        );
+        assert_eq!(
+            error(u8::from_lox(&Value::Number(1.5))),
+            "Expected an integer from 0 to 255 but got 1.5."
+        );
+        assert_eq!(
+            error(u8::from_lox(&Value::Number(256.0))),
+            "Expected an integer from 0 to 255 but got 256."
+        );
+        assert_eq!(
+            Vec::<f64>::from_lox(&vec!["a"].into_lox())
+                .unwrap_err()
+                .to_string(),
+            "Expected a number but got string."
+        );
+    }
+
+    #[test]
+    fn it_rejects_integers_just_out_of_range() {
+        let two_to_the = |power: i32| Value::Number(2f64.powi(power));
+        let negative = |value: Value| Value::Number(-value.as_number().unwrap());
+
+        assert_eq!(i64::from_lox(&negative(two_to_the(63))), Ok(i64::MIN));
+        assert!(i64::from_lox(&two_to_the(63)).is_err());
+        assert!(i64::from_lox(&Value::Number(-2f64.powi(63) - 2048.0)).is_err());
+        assert!(isize::from_lox(&two_to_the(63)).is_err());
+        assert_eq!(u64::from_lox(&two_to_the(63)), Ok(1 << 63));
+        assert!(u64::from_lox(&two_to_the(64)).is_err());
+        assert!(usize::from_lox(&two_to_the(64)).is_err());
+        assert_eq!(i32::from_lox(&negative(two_to_the(31))), Ok(i32::MIN));
+        assert!(i32::from_lox(&two_to_the(31)).is_err());
+    }
+}
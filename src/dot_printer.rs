@@ -0,0 +1,309 @@
+use std::rc::Rc;
+
+use crate::{
+    expr::{Ast, ExprId, LiteralType},
+    printer::Printer,
+    stmt::{FunctionDecl, Stmt},
+    token::Token,
+    visitor::{
+        walk_assign, walk_call, walk_grouping, walk_operands, walk_ternary, walk_unary,
+        StmtVisitor, Visitor,
+    },
+};
+
+/**
+Prints expressions as a [Graphviz](https://graphviz.org/) `digraph` with one node per `Expr`,
+labelled with its operator or literal value, and an edge from each node to its operands. Rendering
+it with e.g. `dot -Tsvg` makes precedence mistakes in large expressions easy to spot.
+
+Visiting a node returns the name it was given in the graph so the parent can draw an edge to it.
+A program is drawn as a single `program` node with an edge to each of its statements.
+*/
+pub struct DotPrinter {
+    statements: Vec<String>,
+    next_node: usize,
+}
+
+impl DotPrinter {
+    pub fn new() -> DotPrinter {
+        DotPrinter {
+            statements: vec![],
+            next_node: 0,
+        }
+    }
+
+    fn node(&mut self, label: &str, children: Vec<String>) -> String {
+        let name = format!("n{}", self.next_node);
+        self.next_node += 1;
+
+        self.statements
+            .push(format!("{name} [label=\"{}\"];", escape(label)));
+        for child in children {
+            self.statements.push(format!("{name} -> {child};"));
+        }
+
+        name
+    }
+
+    fn digraph(&self) -> String {
+        let mut str = String::from("digraph ast {\n");
+        for statement in &self.statements {
+            str.push_str("    ");
+            str.push_str(statement);
+            str.push('\n');
+        }
+        str.push('}');
+
+        str
+    }
+
+    fn clear(&mut self) {
+        self.statements.clear();
+        self.next_node = 0;
+    }
+
+    fn visit_stmts(&mut self, ast: &Ast, statements: &[Stmt]) -> Vec<String> {
+        statements
+            .iter()
+            .map(|statement| self.visit_stmt(ast, statement))
+            .collect()
+    }
+}
+
+impl Default for DotPrinter {
+    fn default() -> DotPrinter {
+        DotPrinter::new()
+    }
+}
+
+impl Printer for DotPrinter {
+    fn print(&mut self, ast: &Ast, expr: ExprId) -> String {
+        self.clear();
+        self.visit_expr(ast, expr);
+        self.digraph()
+    }
+
+    fn print_program(&mut self, ast: &Ast, statements: &[Stmt]) -> String {
+        self.clear();
+        let statements = self.visit_stmts(ast, statements);
+        self.node("program", statements);
+        self.digraph()
+    }
+}
+
+impl StmtVisitor<String> for DotPrinter {
+    fn visit_block_stmt(&mut self, ast: &Ast, statements: &[Stmt]) -> String {
+        let statements = self.visit_stmts(ast, statements);
+        self.node("block", statements)
+    }
+
+    fn visit_expression_stmt(&mut self, ast: &Ast, expr: ExprId) -> String {
+        let expr = self.visit_expr(ast, expr);
+        self.node(";", vec![expr])
+    }
+
+    fn visit_function_stmt(&mut self, ast: &Ast, declaration: &Rc<FunctionDecl>) -> String {
+        let params: Vec<&str> = declaration.params.iter().map(|p| p.text.as_str()).collect();
+        let label = format!("fun {}({})", declaration.name.text, params.join(", "));
+        let body = self.visit_stmts(ast, &declaration.body);
+        self.node(&label, body)
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> String {
+        let mut children = vec![
+            self.visit_expr(ast, condition),
+            self.visit_stmt(ast, then_branch),
+        ];
+        if let Some(else_branch) = else_branch {
+            children.push(self.visit_stmt(ast, else_branch));
+        }
+        self.node("if", children)
+    }
+
+    fn visit_print_stmt(&mut self, ast: &Ast, expr: ExprId) -> String {
+        let expr = self.visit_expr(ast, expr);
+        self.node("print", vec![expr])
+    }
+
+    fn visit_return_stmt(&mut self, ast: &Ast, _keyword: &Token, value: Option<ExprId>) -> String {
+        let value = value.map(|value| self.visit_expr(ast, value));
+        self.node("return", value.into_iter().collect())
+    }
+
+    fn visit_var_stmt(&mut self, ast: &Ast, name: &Token, initializer: Option<ExprId>) -> String {
+        let initializer = initializer.map(|initializer| self.visit_expr(ast, initializer));
+        self.node(
+            &format!("var {}", name.text),
+            initializer.into_iter().collect(),
+        )
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        ast: &Ast,
+        _keyword: &Token,
+        condition: ExprId,
+        body: &Stmt,
+    ) -> String {
+        let condition = self.visit_expr(ast, condition);
+        let body = self.visit_stmt(ast, body);
+        self.node("while", vec![condition, body])
+    }
+}
+
+impl Visitor<String> for DotPrinter {
+    fn visit_assign(&mut self, ast: &Ast, _id: ExprId, name: &Token, value: ExprId) -> String {
+        let value = walk_assign(self, ast, value);
+        self.node(&format!("{} =", name.text), vec![value])
+    }
+
+    fn visit_binary(&mut self, ast: &Ast, left: ExprId, operator: &Token, right: ExprId) -> String {
+        let (left, right) = walk_operands(self, ast, left, right);
+        self.node(&operator.text, vec![left, right])
+    }
+
+    fn visit_call(
+        &mut self,
+        ast: &Ast,
+        callee: ExprId,
+        _paren: &Token,
+        arguments: &[ExprId],
+    ) -> String {
+        let (callee, arguments) = walk_call(self, ast, callee, arguments);
+        let mut children = vec![callee];
+        children.extend(arguments);
+        self.node("call", children)
+    }
+
+    fn visit_comma(&mut self, ast: &Ast, left: ExprId, right: ExprId) -> String {
+        let (left, right) = walk_operands(self, ast, left, right);
+        self.node(",", vec![left, right])
+    }
+
+    fn visit_grouping(&mut self, ast: &Ast, inner: ExprId) -> String {
+        let inner = walk_grouping(self, ast, inner);
+        self.node("group", vec![inner])
+    }
+
+    fn visit_literal(&mut self, _ast: &Ast, literal: &LiteralType) -> String {
+        let label = match literal {
+            LiteralType::Number(n) => n.to_string(),
+            LiteralType::String(s) => format!("\"{s}\""),
+            LiteralType::True(t) => t.to_string(),
+            LiteralType::False(f) => f.to_string(),
+            LiteralType::Nil(()) => String::from("nil"),
+        };
+        self.node(&label, vec![])
+    }
+
+    fn visit_logical(
+        &mut self,
+        ast: &Ast,
+        left: ExprId,
+        operator: &Token,
+        right: ExprId,
+    ) -> String {
+        let (left, right) = walk_operands(self, ast, left, right);
+        self.node(&operator.text, vec![left, right])
+    }
+
+    fn visit_ternary(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    ) -> String {
+        let (condition, then_branch, else_branch) =
+            walk_ternary(self, ast, condition, then_branch, else_branch);
+        self.node("?:", vec![condition, then_branch, else_branch])
+    }
+
+    fn visit_unary(&mut self, ast: &Ast, operator: &Token, right: ExprId) -> String {
+        let right = walk_unary(self, ast, right);
+        self.node(&operator.text, vec![right])
+    }
+
+    fn visit_variable(&mut self, _ast: &Ast, _id: ExprId, name: &Token) -> String {
+        self.node(&name.text, vec![])
+    }
+}
+
+// Labels are DOT string literals, so quotes and backslashes in them have to be escaped.
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DotPrinter;
+    use crate::expr::Ast;
+    use crate::parser::Parser;
+    use crate::printer::Printer;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn it_prints_a_digraph() {
+        let mut scanner = Scanner::new("-1 * (\"a\")");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let expr = Parser::new(tokens, &mut ast).parse_expression().unwrap();
+
+        let mut printer = DotPrinter::new();
+        let output = printer.print(&ast, expr);
+
+        assert_eq!(
+            output,
+            [
+                "digraph ast {",
+                "    n0 [label=\"1\"];",
+                "    n1 [label=\"-\"];",
+                "    n1 -> n0;",
+                "    n2 [label=\"\\\"a\\\"\"];",
+                "    n3 [label=\"group\"];",
+                "    n3 -> n2;",
+                "    n4 [label=\"*\"];",
+                "    n4 -> n1;",
+                "    n4 -> n3;",
+                "}",
+            ]
+            .join("\n")
+        );
+        // Printing again starts a fresh graph.
+        assert_eq!(printer.print(&ast, expr), output);
+    }
+
+    #[test]
+    fn it_prints_a_program() {
+        let mut scanner = Scanner::new("var a = 1; { print a; }");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        assert_eq!(
+            DotPrinter::new().print_program(&ast, &statements),
+            [
+                "digraph ast {",
+                "    n0 [label=\"1\"];",
+                "    n1 [label=\"var a\"];",
+                "    n1 -> n0;",
+                "    n2 [label=\"a\"];",
+                "    n3 [label=\"print\"];",
+                "    n3 -> n2;",
+                "    n4 [label=\"block\"];",
+                "    n4 -> n3;",
+                "    n5 [label=\"program\"];",
+                "    n5 -> n1;",
+                "    n5 -> n4;",
+                "}",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,632 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::callable::{Arity, NativeFunction, NativeResult};
+
+use crate::constant_folder::ConstantFolder;
+use crate::convert::IntoNative;
+use crate::errors;
+use crate::exits;
+use crate::expr::Ast;
+use crate::interpreter::{
+    Budget, Interpreter, RuntimeError, DEFAULT_MAX_CALL_DEPTH, DEFAULT_MAX_STACK,
+};
+use crate::natives::{self, Capabilities};
+use crate::parser::{ParseError, Parser};
+use crate::resolver::{ResolveError, Resolver};
+use crate::scanner::{ScanError, Scanner};
+use crate::stmt::Stmt;
+use crate::token::{Span, Token, TokenType};
+use crate::value::Value;
+
+/**
+Settings for a `Lox` engine.
+
+The capabilities decide which natives scripts get; by default they only get those without any
+effect outside of the script.
+
+The limits make it safe to run scripts that can't be trusted to finish. They apply afresh to each
+call to `eval`, `run`, `interpret` or `reset_budget`, and a script that runs out is stopped with a
+`RuntimeError` whose `limit` says which one it hit:
+
+```
+use rlox::{Config, Lox, LoxError};
+
+let mut lox = Lox::with_config(Config {
+    max_steps: Some(1000),
+    ..Config::default()
+});
+let error = lox.run("while (true) {}").unwrap_err();
+assert!(matches!(error, LoxError::Runtime(e) if e.limit.is_some()));
+```
+*/
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Fold constant expressions before running them.
+    pub optimize: bool,
+    /// The most loop iterations and calls a script may make, counted together.
+    pub max_steps: Option<u64>,
+    /// How long a script may run for.
+    pub timeout: Option<Duration>,
+    /// How deeply calls may nest before a "Stack overflow." error is raised.
+    pub max_call_depth: usize,
+    /// How many bytes of native stack calls may use before a "Stack overflow." error is raised.
+    /// This has to stay well below the stack size of the thread the engine runs on.
+    pub max_stack: usize,
+    /// What the natives installed in the global environment may do.
+    pub capabilities: Capabilities,
+    /// Where the numbers `random` and `random_int` return start from, so that runs with the same
+    /// seed get the same numbers.
+    pub seed: u64,
+    /// The command-line arguments `args` returns, for natives with the `env` capability.
+    pub args: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            optimize: false,
+            max_steps: None,
+            timeout: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_stack: DEFAULT_MAX_STACK,
+            capabilities: Capabilities::none(),
+            seed: 0,
+            args: vec![],
+        }
+    }
+}
+
+/// Anything that can go wrong running source with a `Lox` engine.
+#[derive(Debug)]
+pub enum LoxError {
+    /// The source didn't scan or parse; every error found is kept so they can all be reported.
+    Syntax {
+        scan_errors: Vec<ScanError>,
+        parse_errors: Vec<ParseError>,
+    },
+    /// The source parsed but misuses a variable in a way the resolver catches before it runs.
+    Resolve(Vec<ResolveError>),
+    Runtime(RuntimeError),
+    /// The script called `exit` with this status. Nothing went wrong, so there is nothing to
+    /// report; it is up to the host whether to end the process with the status.
+    Exit(i32),
+    Io(io::Error),
+}
+
+impl LoxError {
+    /// The process exit code from `exits` that a script failing with this error should end with.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoxError::Syntax { .. } | LoxError::Resolve(_) => exits::EX_DATAERR,
+            LoxError::Runtime(error) if error.limit.is_some() => exits::EX_TEMPFAIL,
+            LoxError::Runtime(_) => exits::EX_SOFTWARE,
+            LoxError::Exit(code) => *code,
+            LoxError::Io(_) => exits::EX_NOINPUT,
+        }
+    }
+
+    /// Reports the error to `out` through `errors`, the way the CLI shows it.
+    pub fn report(&self, out: &mut dyn Write) {
+        match self {
+            LoxError::Syntax {
+                scan_errors,
+                parse_errors,
+            } => {
+                for error in scan_errors {
+                    errors::handle(out, error.line, error.message.clone());
+                }
+                for error in parse_errors {
+                    errors::error(out, &error.token, error.message.clone());
+                }
+            }
+            LoxError::Resolve(resolve_errors) => {
+                for error in resolve_errors {
+                    errors::error(out, &error.token, error.message.clone());
+                }
+            }
+            LoxError::Runtime(error) => errors::runtime_error(out, error),
+            LoxError::Exit(_) => {}
+            LoxError::Io(e) => {
+                let _ = writeln!(out, "{e}");
+            }
+        }
+    }
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoxError::Syntax {
+                scan_errors,
+                parse_errors,
+            } => {
+                let scan_errors = scan_errors
+                    .iter()
+                    .map(|e| errors::describe(e.line, "", &e.message));
+                let parse_errors = parse_errors.iter().map(|e| {
+                    errors::describe(e.token.line, &errors::location(&e.token), &e.message)
+                });
+                let lines: Vec<String> = scan_errors.chain(parse_errors).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            LoxError::Resolve(resolve_errors) => {
+                let lines: Vec<String> = resolve_errors
+                    .iter()
+                    .map(|e| {
+                        errors::describe(e.token.line, &errors::location(&e.token), &e.message)
+                    })
+                    .collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            LoxError::Runtime(error) => write!(f, "{}", errors::describe_runtime_error(error)),
+            LoxError::Exit(code) => write!(f, "Exited with status {code}."),
+            LoxError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl Error for LoxError {}
+
+impl From<RuntimeError> for LoxError {
+    fn from(error: RuntimeError) -> LoxError {
+        match error.exit {
+            Some(code) => LoxError::Exit(code),
+            None => LoxError::Runtime(error),
+        }
+    }
+}
+
+impl From<io::Error> for LoxError {
+    fn from(error: io::Error) -> LoxError {
+        LoxError::Io(error)
+    }
+}
+
+/**
+An interpreter session for embedding rlox in another program.
+
+Everything run with the same engine shares one global environment, so a host can define variables,
+run scripts that use them, and read back what the scripts left behind:
+
+```
+use rlox::{Lox, Value};
+
+let mut lox = Lox::new();
+lox.define("width", Value::Number(3.0));
+lox.run("var area = width * width;").unwrap();
+assert_eq!(lox.eval("area + 1").unwrap(), Value::Number(10.0));
+```
+
+The engine also owns the `Ast` that everything it parses is added to, which keeps the nodes of
+earlier chunks of source alive for as long as the values that might refer to them.
+
+What scripts `print` goes to standard output and the errors `report` describes go to standard error,
+unless `set_output` and `set_diagnostics` send them somewhere else.
+*/
+pub struct Lox {
+    ast: Ast,
+    interpreter: Interpreter,
+    config: Config,
+    diagnostics: Box<dyn Write>,
+}
+
+impl Lox {
+    pub fn new() -> Lox {
+        Lox::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> Lox {
+        let budget = Budget {
+            max_steps: config.max_steps,
+            timeout: config.timeout,
+            max_call_depth: config.max_call_depth,
+            max_stack: config.max_stack,
+        };
+
+        let mut lox = Lox {
+            ast: Ast::new(),
+            interpreter: Interpreter::with_budget(budget),
+            config: config.clone(),
+            diagnostics: Box::new(io::stderr()),
+        };
+        natives::install(&mut lox, &config);
+        lox
+    }
+
+    /// Runs `source` and returns the value of its last statement if that is an expression, or
+    /// `nil` otherwise. As in the REPL, the final expression may leave off its `;`.
+    pub fn eval(&mut self, source: &str) -> Result<Value, LoxError> {
+        let statements = self.parse_repl(source)?;
+
+        self.interpreter.reset_budget();
+        let mut value = Value::Nil;
+        for statement in &statements {
+            value = self.execute_statement(statement)?.unwrap_or(Value::Nil);
+        }
+
+        Ok(value)
+    }
+
+    /// Runs `source` as a script.
+    pub fn run(&mut self, source: &str) -> Result<(), LoxError> {
+        let statements = self.parse(source)?;
+        self.interpret(&statements)?;
+        Ok(())
+    }
+
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<(), LoxError> {
+        let source = fs::read_to_string(path)?;
+        self.run(&source)
+    }
+
+    /// Scans, parses and resolves `source` as a script, adding its nodes to `ast`.
+    pub fn parse(&mut self, source: &str) -> Result<Vec<Stmt>, LoxError> {
+        self.parse_with(source, false)
+    }
+
+    /// Scans and parses `source` as REPL input; see `Parser::parse_repl`.
+    pub fn parse_repl(&mut self, source: &str) -> Result<Vec<Stmt>, LoxError> {
+        self.parse_with(source, true)
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), LoxError> {
+        self.interpreter.reset_budget();
+        Ok(self.interpreter.interpret(&self.ast, statements)?)
+    }
+
+    /**
+    Executes one statement, returning its value if it is an expression statement.
+
+    Statements run this way share a budget, so that running a program one statement at a time
+    doesn't give it more to spend than running it all at once; call `reset_budget` before each
+    program.
+    */
+    pub fn execute(&mut self, statement: &Stmt) -> Result<Option<Value>, LoxError> {
+        Ok(self.execute_statement(statement)?)
+    }
+
+    /// Gives the statements run by `execute` from now on the full budget again.
+    pub fn reset_budget(&mut self) {
+        self.interpreter.reset_budget();
+    }
+
+    /// The global variable `name`, if it is defined.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let token = Token::new(
+            TokenType::Identifier,
+            String::from(name),
+            String::from(name),
+            0,
+            Span::default(),
+        );
+        self.interpreter.globals().borrow().get(&token).ok()
+    }
+
+    /// Defines the global variable `name`, replacing any existing definition.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.interpreter.globals().borrow_mut().define(name, value);
+    }
+
+    /**
+    Defines a global function `name` that runs `function`, so scripts can call into the host.
+
+    Calls with the wrong number of arguments for a `Fixed` arity are rejected before `function` is
+    run; a `Variadic` function receives however many were passed. An `Err` returned by `function`
+    becomes a runtime error at the call.
+
+    ```
+    use rlox::{Arity, Lox, Value};
+
+    let mut lox = Lox::new();
+    lox.define_native("double", Arity::Fixed(1), |arguments| {
+        Ok(Value::Number(arguments[0].as_number()? * 2.0))
+    });
+    assert_eq!(lox.eval("double(21)").unwrap(), Value::Number(42.0));
+    assert!(lox.eval("double(\"a\")").is_err());
+    ```
+    */
+    pub fn define_native<F>(&mut self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(&[Value]) -> NativeResult + 'static,
+    {
+        let native = NativeFunction::new(name, arity, function);
+        self.define(name, Value::Native(Rc::new(native)));
+    }
+
+    /**
+    Defines a global function `name` that runs `function`, converting its arguments from Lox values
+    and its result back with `FromLox` and `IntoLox`. The arity is the closure's number of
+    parameters, and an argument of the wrong type is reported as a runtime error at the call.
+
+    ```
+    use rlox::{Lox, Value};
+
+    let mut lox = Lox::new();
+    lox.define_function("repeat", |s: String, times: usize| s.repeat(times));
+    assert_eq!(
+        lox.eval("repeat(\"ab\", 2)").unwrap(),
+        Value::String(String::from("abab"))
+    );
+    assert!(lox.eval("repeat(\"ab\", 1.5)").is_err());
+    ```
+    */
+    pub fn define_function<Args, F: IntoNative<Args>>(&mut self, name: &str, function: F) {
+        let native = function.into_native(name);
+        self.define(name, Value::Native(Rc::new(native)));
+    }
+
+    /// Every global variable and its value, sorted by name.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        let globals = self.interpreter.globals();
+        let bindings = globals
+            .borrow()
+            .bindings()
+            .into_iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        bindings
+    }
+
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    /// Sends what scripts `print` to `output`; see `output::Buffer` for capturing it.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.interpreter.set_output(Box::new(output));
+    }
+
+    /// Sends the errors `report` describes to `diagnostics`.
+    pub fn set_diagnostics(&mut self, diagnostics: impl Write + 'static) {
+        self.diagnostics = Box::new(diagnostics);
+    }
+
+    /// Reports `error` to the diagnostics sink, the way the CLI shows it.
+    pub fn report(&mut self, error: &LoxError) {
+        error.report(&mut *self.diagnostics);
+    }
+
+    fn execute_statement(&mut self, statement: &Stmt) -> Result<Option<Value>, RuntimeError> {
+        match statement {
+            Stmt::Expression(expr) => self.interpreter.evaluate(&self.ast, *expr).map(Some),
+            _ => self
+                .interpreter
+                .execute(&self.ast, statement)
+                .map(|()| None),
+        }
+    }
+
+    fn parse_with(&mut self, source: &str, repl: bool) -> Result<Vec<Stmt>, LoxError> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let scan_errors = scanner.errors().to_vec();
+
+        // Parsing carries on after scan errors so that parse errors are reported alongside them.
+        let mut parser = Parser::new(tokens, &mut self.ast);
+        let parsed = if repl {
+            parser.parse_repl()
+        } else {
+            parser.parse()
+        };
+        let statements = match parsed {
+            Ok(statements) if scan_errors.is_empty() => statements,
+            parsed => {
+                return Err(LoxError::Syntax {
+                    scan_errors,
+                    parse_errors: parsed.err().unwrap_or_default(),
+                })
+            }
+        };
+
+        let statements = if self.config.optimize {
+            ConstantFolder {}.fold_program(&mut self.ast, statements)
+        } else {
+            statements
+        };
+
+        // Folding replaces nodes, so the program is resolved as it will be run.
+        let locals = Resolver::new()
+            .resolve(&self.ast, &statements)
+            .map_err(LoxError::Resolve)?;
+        self.interpreter.resolve(locals);
+        Ok(statements)
+    }
+}
+
+impl Default for Lox {
+    fn default() -> Lox {
+        Lox::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::{Config, Lox, LoxError};
+    use crate::callable::Arity;
+    use crate::exits;
+    use crate::output::Buffer;
+    use crate::value::Value;
+
+    #[test]
+    fn it_evaluates_to_the_last_expression() {
+        let mut lox = Lox::new();
+
+        assert_eq!(lox.eval("var a = 2; a * 3").unwrap(), Value::Number(6.0));
+        assert_eq!(lox.eval("var b = a;").unwrap(), Value::Nil);
+        assert_eq!(lox.get("b"), Some(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn it_shares_globals_with_the_host() {
+        let mut lox = Lox::new();
+        lox.define("name", Value::String(String::from("lox")));
+
+        lox.run("var greeting = \"hi \" + name;").unwrap();
+
+        // The natives and constants every engine starts with are globals too.
+        let builtins: Vec<String> = Lox::new()
+            .globals()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let globals: Vec<(String, Value)> = lox
+            .globals()
+            .into_iter()
+            .filter(|(name, _)| !builtins.contains(name))
+            .collect();
+        assert_eq!(
+            globals,
+            [
+                (
+                    String::from("greeting"),
+                    Value::String(String::from("hi lox"))
+                ),
+                (String::from("name"), Value::String(String::from("lox"))),
+            ]
+        );
+        assert_eq!(lox.get("missing"), None);
+    }
+
+    #[test]
+    fn it_calls_natives() {
+        let mut lox = Lox::new();
+        lox.define_native("sum", Arity::Variadic, |arguments| {
+            let mut sum = 0.0;
+            for argument in arguments {
+                sum += argument.as_number()?;
+            }
+            Ok(Value::Number(sum))
+        });
+        lox.define_native("greet", Arity::Fixed(1), |arguments| {
+            Ok(Value::String(format!("hi {}", arguments[0].as_str()?)))
+        });
+
+        assert_eq!(lox.eval("sum()").unwrap(), Value::Number(0.0));
+        assert_eq!(lox.eval("sum(1, 2, 3)").unwrap(), Value::Number(6.0));
+        assert_eq!(
+            lox.eval("greet(\"lox\")").unwrap(),
+            Value::String(String::from("hi lox"))
+        );
+        assert_eq!(lox.eval("greet").unwrap().to_string(), "<native fn>");
+        assert_eq!(lox.eval("greet == greet").unwrap(), Value::Boolean(true));
+        assert_eq!(lox.eval("greet == sum").unwrap(), Value::Boolean(false));
+    }
+
+    #[test]
+    fn it_reports_errors_from_calls() {
+        let mut lox = Lox::new();
+        lox.define_native("greet", Arity::Fixed(1), |arguments| {
+            Ok(Value::String(format!("hi {}", arguments[0].as_str()?)))
+        });
+
+        let message = |lox: &mut Lox, source: &str| match lox.eval(source) {
+            Err(LoxError::Runtime(error)) => error.message,
+            result => panic!("{source:?} gave {result:?}"),
+        };
+
+        assert_eq!(
+            message(&mut lox, "greet()"),
+            "Expected 1 arguments but got 0."
+        );
+        assert_eq!(
+            message(&mut lox, "greet(1)"),
+            "Expected a string but got number."
+        );
+        assert_eq!(
+            message(&mut lox, "\"greet\"(1)"),
+            "Can only call functions and classes."
+        );
+    }
+
+    #[test]
+    fn it_reports_errors() {
+        let mut lox = Lox::new();
+
+        let error = lox.eval("1 +; \"open").unwrap_err();
+        assert!(matches!(
+            &error,
+            LoxError::Syntax { scan_errors, parse_errors }
+                if scan_errors.len() == 1 && parse_errors.len() == 1
+        ));
+        assert_eq!(error.exit_code(), exits::EX_DATAERR);
+
+        let error = lox.eval("-\"a\"").unwrap_err();
+        assert_eq!(error.to_string(), "Operand must be a number.\n[line 1]");
+        assert_eq!(error.exit_code(), exits::EX_SOFTWARE);
+
+        let error = lox.run_file("/nonexistent/script.lox").unwrap_err();
+        assert_eq!(error.exit_code(), exits::EX_NOINPUT);
+    }
+
+    #[test]
+    fn it_writes_to_the_sinks_it_is_given() {
+        let (output, diagnostics) = (Buffer::new(), Buffer::new());
+        let mut lox = Lox::new();
+        lox.set_output(output.clone());
+        lox.set_diagnostics(diagnostics.clone());
+
+        let error = lox
+            .run("print \"before\"; print -nil; print \"after\";")
+            .unwrap_err();
+        lox.report(&error);
+        let error = lox.run("print (1;").unwrap_err();
+        lox.report(&error);
+
+        assert_eq!(output.contents(), "before\n");
+        assert_eq!(
+            diagnostics.contents(),
+            "Operand must be a number.\n[line 1]\n[line 1] Error at ';': Expect ')' after expression.\n"
+        );
+    }
+
+    #[test]
+    fn it_gives_each_run_a_fresh_budget() {
+        let mut lox = Lox::with_config(Config {
+            max_steps: Some(10),
+            ..Config::default()
+        });
+        lox.define_native("f", Arity::Fixed(0), |_| Ok(Value::Nil));
+
+        for _ in 0..3 {
+            lox.run("for (var i = 0; i < 5; i = i + 1) f();").unwrap();
+        }
+
+        let error = lox
+            .eval("var i = 0; while (i < 11) i = i + 1;")
+            .unwrap_err();
+        assert!(matches!(&error, LoxError::Runtime(e) if e.limit.is_some()));
+        assert_eq!(error.exit_code(), exits::EX_TEMPFAIL);
+    }
+
+    #[test]
+    fn it_never_overflows_the_native_stack() {
+        // The smallest stack Rust gives a thread by default, so the test doesn't depend on the one
+        // the test harness happens to run it on.
+        let thread = thread::Builder::new().stack_size(2 << 20).spawn(|| {
+            let mut lox = Lox::new();
+            for source in [
+                "fun f(n) { return f(n + 1); } f(0);",
+                "fun f() { return f() + f(); } f();",
+                "fun f(n) { if (true) { while (true) { return 1 + (2 * -f(n)); } } } f(0);",
+                "fun a() { return b() + 1; } fun b() { { { return -a(); } } } a();",
+                "fun f() { fun g() { return f(); } return g(); } f();",
+            ] {
+                match lox.run(source) {
+                    Err(LoxError::Runtime(error)) => assert_eq!(error.message, "Stack overflow."),
+                    result => panic!("{source:?} gave {result:?}"),
+                }
+            }
+            assert_eq!(lox.eval("1 + 1").unwrap(), Value::Number(2.0));
+        });
+
+        thread.unwrap().join().unwrap();
+    }
+}
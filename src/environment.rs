@@ -0,0 +1,115 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::interpreter::RuntimeError;
+use crate::token::Token;
+use crate::value::Value;
+
+/**
+Bindings of variable names to values for one scope, following
+[8.3 Environments](https://craftinginterpreters.com/statements-and-state.html#environments).
+
+Scopes are shared between whoever created them and the scopes nested inside them, so an enclosing
+scope is held through `Rc<RefCell<_>>` where `jlox` would simply hold a reference.
+*/
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment {
+            values: HashMap::new(),
+            enclosing: None,
+        }
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(String::from(name), value);
+    }
+
+    /// The variables defined in this scope, not counting enclosing ones, sorted by name.
+    pub fn bindings(&self) -> Vec<(&String, &Value)> {
+        let mut bindings: Vec<(&String, &Value)> = self.values.iter().collect();
+        bindings.sort_by(|a, b| a.0.cmp(b.0));
+        bindings
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.values.get(&name.text) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(RuntimeError::new(
+                name,
+                &format!("Undefined variable '{}'.", name.text),
+            )),
+        }
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(&name.text) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(RuntimeError::new(
+                name,
+                &format!("Undefined variable '{}'.", name.text),
+            )),
+        }
+    }
+
+    /**
+    The value of `name` in the scope `distance` scopes out from this one, as found by the resolver;
+    see [11.4.1 Interpreting resolved variables](https://craftinginterpreters.com/resolving-and-binding.html#interpreting-resolved-variables).
+    */
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value, RuntimeError> {
+        match distance {
+            0 => self.get(name),
+            _ => self.ancestor(distance).borrow().get(name),
+        }
+    }
+
+    /// Assigns to `name` in the scope `distance` scopes out from this one, like `get_at`.
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        match distance {
+            0 => self.assign(name, value),
+            _ => self.ancestor(distance).borrow_mut().assign(name, value),
+        }
+    }
+
+    // The scope `distance` scopes out, which must be at least 1 since a scope can't hand out an
+    // `Rc` to itself.
+    fn ancestor(&self, distance: usize) -> Rc<RefCell<Environment>> {
+        let mut environment = Rc::clone(
+            self.enclosing
+                .as_ref()
+                .expect("the resolver only counts scopes that exist"),
+        );
+        for _ in 1..distance {
+            let enclosing = environment.borrow().enclosing.clone();
+            environment = enclosing.expect("the resolver only counts scopes that exist");
+        }
+        environment
+    }
+}
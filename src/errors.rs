@@ -1,7 +1,81 @@
-pub fn handle(line: usize, message: String) {
-    report(line, String::new(), message);
+use std::io::Write;
+
+use crate::interpreter::RuntimeError;
+use crate::token::{Token, TokenType};
+
+// Errors are reported to a diagnostics sink, which is standard error for the CLI. There's nowhere
+// left to report a failure to write a diagnostic, so it's dropped.
+
+pub fn handle(out: &mut dyn Write, line: usize, message: String) {
+    report(out, line, String::new(), message);
+}
+
+pub fn error(out: &mut dyn Write, token: &Token, message: String) {
+    report(out, token.line, location(token), message);
 }
 
-fn report(line: usize, location: String, message: String) {
-    eprintln!("[line {line}] Error {location}: {message}");
+fn report(out: &mut dyn Write, line: usize, location: String, message: String) {
+    let _ = writeln!(out, "{}", describe(line, &location, &message));
+}
+
+pub fn runtime_error(out: &mut dyn Write, error: &RuntimeError) {
+    let _ = writeln!(out, "{}", describe_runtime_error(error));
+}
+
+/// The text `handle` and `error` report, for callers that show errors some other way.
+pub fn describe(line: usize, location: &str, message: &str) -> String {
+    format!("[line {line}] Error {location}: {message}")
+}
+
+/**
+Describes a runtime error along with the calls it was raised in, innermost first, e.g.
+
+```text
+Operand must be a number.
+[line 2] in inner()
+[line 5] in outer()
+[line 7] in script
+```
+
+An error raised outside of any function is described by its line alone. The middle of a very deep
+stack, such as one that overflowed, is left out.
+*/
+pub fn describe_runtime_error(error: &RuntimeError) -> String {
+    let Some(innermost) = error.stack.first() else {
+        return format!("{}\n[line {}]", error.message, error.token.line);
+    };
+
+    // Each frame holds the line of a call, which is in the function of the frame after it.
+    let mut lines = vec![format!(
+        "[line {}] in {}()",
+        error.token.line, innermost.function
+    )];
+    for (i, frame) in error.stack.iter().enumerate() {
+        let caller = match error.stack.get(i + 1) {
+            Some(caller) => format!("{}()", caller.function),
+            None => String::from("script"),
+        };
+        lines.push(format!("[line {}] in {caller}", frame.line));
+    }
+
+    if lines.len() > 2 * TRACE_EDGE {
+        let omitted = lines.len() - 2 * TRACE_EDGE;
+        lines.splice(
+            TRACE_EDGE..lines.len() - TRACE_EDGE,
+            [format!("[{omitted} more calls]")],
+        );
+    }
+
+    format!("{}\n{}", error.message, lines.join("\n"))
+}
+
+// How many lines of a stack trace to keep at either end.
+const TRACE_EDGE: usize = 10;
+
+pub fn location(token: &Token) -> String {
+    if token.token_type == TokenType::Eof {
+        String::from("at end")
+    } else {
+        format!("at '{}'", token.text)
+    }
 }
@@ -1,5 +1,9 @@
 #![allow(dead_code)]
 
 // See https://www.freebsd.org/cgi/man.cgi?query=sysexits for details
+pub const EX_OK: i32 = 0;
 pub const EX_USAGE: i32 = 64;
 pub const EX_DATAERR: i32 = 65;
+pub const EX_NOINPUT: i32 = 66;
+pub const EX_SOFTWARE: i32 = 70;
+pub const EX_TEMPFAIL: i32 = 75;
@@ -1,25 +1,165 @@
 #![allow(dead_code)]
-use crate::token::Token;
+use std::collections::HashMap;
+use std::mem;
+use std::ops::{Index, IndexMut};
 
+use serde::{Deserialize, Serialize};
+
+use crate::token::{Span, Token};
+
+/**
+A handle to an `Expr` stored in an `Ast`.
+
+IDs are only meaningful for the `Ast` that handed them out. Because they are small `Copy` values they
+make cheap keys for side tables (e.g. `HashMap<ExprId, T>`) that hold per-node data computed by a
+pass over the tree, such as resolved scope depths, without having to touch the tree itself.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExprId(usize);
+
+impl ExprId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum Expr {
+    Assign {
+        name: Token,
+        value: ExprId,
+    },
     Binary {
-        left: Box<Expr>,
+        left: ExprId,
         operator: Token,
-        right: Box<Expr>,
+        right: ExprId,
+    },
+    Call {
+        callee: ExprId,
+        /// The closing parenthesis, whose location is used to report errors from the call.
+        paren: Token,
+        arguments: Vec<ExprId>,
+    },
+    Comma {
+        left: ExprId,
+        right: ExprId,
     },
-    Grouping(Box<Expr>),
+    Grouping(ExprId),
     Literal(LiteralType),
+    /// `and` and `or`, which unlike `Binary` operators don't always evaluate their right operand.
+    Logical {
+        left: ExprId,
+        operator: Token,
+        right: ExprId,
+    },
+    Ternary {
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    },
     Unary {
         operator: Token,
-        right: Box<Expr>,
+        right: ExprId,
     },
+    Variable(Token),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
 pub enum LiteralType {
     // TODO: Look into improving this.
-    Number(f64),
+    Number(#[serde(with = "crate::json::number")] f64),
     String(String),
     True(bool),
     False(bool),
     Nil(()),
 }
+
+/**
+The arena that owns every `Expr` node. Children refer to each other by `ExprId` rather than by
+`Box<Expr>`, so a tree can be walked any number of times by borrowing the `Ast`.
+
+Nodes are only ever appended, which means a child is always added before its parent and an `ExprId`
+stays valid for as long as the `Ast` lives.
+
+The source span of each node is kept in a side table since only nodes that came from the parser have
+one; nodes built by a pass (e.g. `ConstantFolder`) don't.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Ast {
+    exprs: Vec<Expr>,
+    spans: HashMap<ExprId, Span>,
+}
+
+impl Ast {
+    pub fn new() -> Ast {
+        Ast {
+            exprs: vec![],
+            spans: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, expr: Expr) -> ExprId {
+        self.exprs.push(expr);
+        ExprId(self.exprs.len() - 1)
+    }
+
+    /// Swaps the node behind `id` for `expr`, returning the node that was there.
+    pub fn replace(&mut self, id: ExprId, expr: Expr) -> Expr {
+        mem::replace(&mut self.exprs[id.0], expr)
+    }
+
+    pub fn span(&self, id: ExprId) -> Option<Span> {
+        self.spans.get(&id).copied()
+    }
+
+    pub fn set_span(&mut self, id: ExprId, span: Span) {
+        self.spans.insert(id, span);
+    }
+
+    pub fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+}
+
+impl Index<ExprId> for Ast {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.0]
+    }
+}
+
+impl IndexMut<ExprId> for Ast {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expr {
+        &mut self.exprs[id.0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Ast, Expr, ExprId, LiteralType};
+
+    #[test]
+    fn it_keys_side_tables_by_expr_id() {
+        let mut ast = Ast::new();
+        let one = ast.add(Expr::Literal(LiteralType::Number(1.0)));
+        let grouping = ast.add(Expr::Grouping(one));
+
+        let mut depths: HashMap<ExprId, usize> = HashMap::new();
+        depths.insert(grouping, 0);
+        if let Expr::Grouping(inner) = &ast[grouping] {
+            depths.insert(*inner, depths[&grouping] + 1);
+        }
+
+        assert_eq!(ast.len(), 2);
+        assert_eq!(depths[&one], 1);
+        assert!(one < grouping);
+    }
+}
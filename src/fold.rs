@@ -0,0 +1,298 @@
+use std::rc::Rc;
+
+use crate::expr::{Ast, Expr, ExprId, LiteralType};
+use crate::stmt::{FunctionDecl, Stmt};
+use crate::token::Token;
+
+/**
+Rewrites an expression tree, or the statements of a program, into a new one.
+
+Each `fold_*` hook receives the parts of a node by value and returns the `ExprId` of its
+replacement. The default hooks fold the children and rebuild an identical node, so a pass only
+overrides the variants it rewrites. A hook is free to return an existing ID instead of allocating,
+e.g. returning the folded child of a grouping to drop the parentheses.
+
+New nodes are appended to the same `Ast`, so the original tree is left untouched and both trees can
+be compared or printed afterwards.
+
+Statements are folded the same way through `fold_stmt`, except that the hooks return the new `Stmt`
+itself since statements don't live in the arena.
+*/
+pub trait Folder {
+    fn fold_stmt(&mut self, ast: &mut Ast, stmt: Stmt) -> Stmt {
+        match stmt {
+            Stmt::Block(statements) => self.fold_block_stmt(ast, statements),
+            Stmt::Expression(expr) => self.fold_expression_stmt(ast, expr),
+            Stmt::Function(declaration) => self.fold_function_stmt(ast, declaration),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.fold_if_stmt(ast, condition, *then_branch, else_branch.map(|e| *e)),
+            Stmt::Print(expr) => self.fold_print_stmt(ast, expr),
+            Stmt::Return { keyword, value } => self.fold_return_stmt(ast, keyword, value),
+            Stmt::Var { name, initializer } => self.fold_var_stmt(ast, name, initializer),
+            Stmt::While {
+                keyword,
+                condition,
+                body,
+            } => self.fold_while_stmt(ast, keyword, condition, *body),
+        }
+    }
+
+    fn fold_block_stmt(&mut self, ast: &mut Ast, statements: Vec<Stmt>) -> Stmt {
+        let statements = statements
+            .into_iter()
+            .map(|statement| self.fold_stmt(ast, statement))
+            .collect();
+        Stmt::Block(statements)
+    }
+
+    fn fold_expression_stmt(&mut self, ast: &mut Ast, expr: ExprId) -> Stmt {
+        Stmt::Expression(self.fold_expr(ast, expr))
+    }
+
+    fn fold_function_stmt(&mut self, ast: &mut Ast, declaration: Rc<FunctionDecl>) -> Stmt {
+        let body = declaration
+            .body
+            .iter()
+            .map(|statement| self.fold_stmt(ast, statement.clone()))
+            .collect();
+        Stmt::Function(Rc::new(FunctionDecl {
+            name: declaration.name.clone(),
+            params: declaration.params.clone(),
+            body,
+        }))
+    }
+
+    fn fold_if_stmt(
+        &mut self,
+        ast: &mut Ast,
+        condition: ExprId,
+        then_branch: Stmt,
+        else_branch: Option<Stmt>,
+    ) -> Stmt {
+        let condition = self.fold_expr(ast, condition);
+        let then_branch = Box::new(self.fold_stmt(ast, then_branch));
+        let else_branch = else_branch.map(|else_branch| Box::new(self.fold_stmt(ast, else_branch)));
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        }
+    }
+
+    fn fold_print_stmt(&mut self, ast: &mut Ast, expr: ExprId) -> Stmt {
+        Stmt::Print(self.fold_expr(ast, expr))
+    }
+
+    fn fold_return_stmt(&mut self, ast: &mut Ast, keyword: Token, value: Option<ExprId>) -> Stmt {
+        let value = value.map(|value| self.fold_expr(ast, value));
+        Stmt::Return { keyword, value }
+    }
+
+    fn fold_var_stmt(&mut self, ast: &mut Ast, name: Token, initializer: Option<ExprId>) -> Stmt {
+        let initializer = initializer.map(|initializer| self.fold_expr(ast, initializer));
+        Stmt::Var { name, initializer }
+    }
+
+    fn fold_while_stmt(
+        &mut self,
+        ast: &mut Ast,
+        keyword: Token,
+        condition: ExprId,
+        body: Stmt,
+    ) -> Stmt {
+        let condition = self.fold_expr(ast, condition);
+        let body = Box::new(self.fold_stmt(ast, body));
+        Stmt::While {
+            keyword,
+            condition,
+            body,
+        }
+    }
+
+    fn fold_expr(&mut self, ast: &mut Ast, expr: ExprId) -> ExprId {
+        match ast[expr].clone() {
+            Expr::Assign { name, value } => self.fold_assign(ast, name, value),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => self.fold_binary(ast, left, operator, right),
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => self.fold_call(ast, callee, paren, arguments),
+            Expr::Comma { left, right } => self.fold_comma(ast, left, right),
+            Expr::Grouping(inner) => self.fold_grouping(ast, inner),
+            Expr::Literal(literal) => self.fold_literal(ast, literal),
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => self.fold_logical(ast, left, operator, right),
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.fold_ternary(ast, condition, then_branch, else_branch),
+            Expr::Unary { operator, right } => self.fold_unary(ast, operator, right),
+            Expr::Variable(name) => self.fold_variable(ast, name),
+        }
+    }
+
+    fn fold_assign(&mut self, ast: &mut Ast, name: Token, value: ExprId) -> ExprId {
+        let value = self.fold_expr(ast, value);
+        ast.add(Expr::Assign { name, value })
+    }
+
+    fn fold_binary(
+        &mut self,
+        ast: &mut Ast,
+        left: ExprId,
+        operator: Token,
+        right: ExprId,
+    ) -> ExprId {
+        let left = self.fold_expr(ast, left);
+        let right = self.fold_expr(ast, right);
+        ast.add(Expr::Binary {
+            left,
+            operator,
+            right,
+        })
+    }
+
+    fn fold_call(
+        &mut self,
+        ast: &mut Ast,
+        callee: ExprId,
+        paren: Token,
+        arguments: Vec<ExprId>,
+    ) -> ExprId {
+        let callee = self.fold_expr(ast, callee);
+        let arguments = arguments
+            .into_iter()
+            .map(|argument| self.fold_expr(ast, argument))
+            .collect();
+        ast.add(Expr::Call {
+            callee,
+            paren,
+            arguments,
+        })
+    }
+
+    fn fold_comma(&mut self, ast: &mut Ast, left: ExprId, right: ExprId) -> ExprId {
+        let left = self.fold_expr(ast, left);
+        let right = self.fold_expr(ast, right);
+        ast.add(Expr::Comma { left, right })
+    }
+
+    fn fold_grouping(&mut self, ast: &mut Ast, inner: ExprId) -> ExprId {
+        let inner = self.fold_expr(ast, inner);
+        ast.add(Expr::Grouping(inner))
+    }
+
+    fn fold_literal(&mut self, ast: &mut Ast, literal: LiteralType) -> ExprId {
+        ast.add(Expr::Literal(literal))
+    }
+
+    fn fold_logical(
+        &mut self,
+        ast: &mut Ast,
+        left: ExprId,
+        operator: Token,
+        right: ExprId,
+    ) -> ExprId {
+        let left = self.fold_expr(ast, left);
+        let right = self.fold_expr(ast, right);
+        ast.add(Expr::Logical {
+            left,
+            operator,
+            right,
+        })
+    }
+
+    fn fold_ternary(
+        &mut self,
+        ast: &mut Ast,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    ) -> ExprId {
+        let condition = self.fold_expr(ast, condition);
+        let then_branch = self.fold_expr(ast, then_branch);
+        let else_branch = self.fold_expr(ast, else_branch);
+        ast.add(Expr::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn fold_unary(&mut self, ast: &mut Ast, operator: Token, right: ExprId) -> ExprId {
+        let right = self.fold_expr(ast, right);
+        ast.add(Expr::Unary { operator, right })
+    }
+
+    fn fold_variable(&mut self, ast: &mut Ast, name: Token) -> ExprId {
+        ast.add(Expr::Variable(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Folder;
+    use crate::ast_printer::AstPrinter;
+    use crate::expr::{Ast, ExprId};
+    use crate::parser::Parser;
+    use crate::printer::Printer;
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str, ast: &mut Ast) -> ExprId {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        Parser::new(tokens, ast).parse_expression().unwrap()
+    }
+
+    struct Identity {}
+
+    impl Folder for Identity {}
+
+    struct Ungroup {}
+
+    impl Folder for Ungroup {
+        fn fold_grouping(&mut self, ast: &mut Ast, inner: ExprId) -> ExprId {
+            self.fold_expr(ast, inner)
+        }
+    }
+
+    #[test]
+    fn it_copies_the_tree_by_default() {
+        let mut ast = Ast::new();
+        let expr = parse("1, true ? -(2) : 3 * 4", &mut ast);
+
+        let folded = Identity {}.fold_expr(&mut ast, expr);
+
+        assert_ne!(folded, expr);
+        assert_eq!(
+            AstPrinter {}.print(&ast, folded),
+            AstPrinter {}.print(&ast, expr)
+        );
+    }
+
+    #[test]
+    fn it_rewrites_only_the_overridden_variants() {
+        let mut ast = Ast::new();
+        let expr = parse("((1 + 2)) * -(3)", &mut ast);
+
+        let folded = Ungroup {}.fold_expr(&mut ast, expr);
+
+        assert_eq!(AstPrinter {}.print(&ast, folded), "(* (+ 1 2) (- 3))");
+        assert_eq!(
+            AstPrinter {}.print(&ast, expr),
+            "(* (group (group (+ 1 2))) (- (group 3)))"
+        );
+    }
+}
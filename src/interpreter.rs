@@ -0,0 +1,887 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hint;
+use std::io::{self, Write};
+use std::mem;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::{
+    callable::{Arity, LoxFunction, NativeError},
+    environment::Environment,
+    expr::{Ast, ExprId, LiteralType},
+    stmt::{FunctionDecl, Stmt},
+    token::{Token, TokenType},
+    value::Value,
+    visitor::{walk_grouping, StmtVisitor, Visitor},
+};
+
+/// An error raised while evaluating, pointing at the token of the operation that failed.
+#[derive(Debug)]
+pub struct RuntimeError {
+    /// Boxed to keep small the `Result` every evaluation returns, and so the native stack used by
+    /// each call.
+    pub token: Box<Token>,
+    pub message: String,
+    /// The limit that stopped the script, when it was stopped for running too long rather than
+    /// for doing something wrong.
+    pub limit: Option<Limit>,
+    /// The calls the error unwound through, innermost first.
+    pub stack: Vec<Frame>,
+    /// The status the script asked to end with, when it was stopped by calling `exit` rather than
+    /// by an error.
+    pub exit: Option<i32>,
+}
+
+impl RuntimeError {
+    pub fn new(token: &Token, message: &str) -> RuntimeError {
+        RuntimeError {
+            token: Box::new(token.clone()),
+            message: String::from(message),
+            limit: None,
+            stack: vec![],
+            exit: None,
+        }
+    }
+
+    fn exceeded(token: &Token, limit: Limit, message: &str) -> RuntimeError {
+        RuntimeError {
+            limit: Some(limit),
+            ..RuntimeError::new(token, message)
+        }
+    }
+
+    fn exit(token: &Token, code: i32) -> RuntimeError {
+        RuntimeError {
+            exit: Some(code),
+            ..RuntimeError::new(token, &format!("Exited with status {code}."))
+        }
+    }
+}
+
+/// A call to a Lox function that was in progress when an error was raised.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// The name of the function called.
+    pub function: String,
+    /// The line the call was made from.
+    pub line: usize,
+}
+
+/// The resource limits a script can be stopped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Steps,
+    Time,
+}
+
+/**
+How much a script may do before it is stopped.
+
+A step is one iteration of a loop or one call. Those are the only ways for a script to keep running
+for longer than it takes to read it, so counting them is enough to stop e.g. `while (true) {}`
+without paying for a check on every expression. The deadline is checked at the same points.
+
+Calls are also limited in how deeply they may nest. Each Lox call takes up dozens of frames of the
+interpreter's own stack, so unbounded recursion in a script would otherwise overflow the native stack
+and abort the whole process. A call nested more than `max_call_depth` deep, or made once the calls
+in progress have used more than `max_stack` bytes of native stack, raises a "Stack overflow."
+runtime error instead, which the host can handle like any other. The depth is the limit scripts can
+rely on; the stack size is the backstop, since how much stack a call uses depends on how deeply its
+body nests and on how rlox was compiled.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    pub max_steps: Option<u64>,
+    pub timeout: Option<Duration>,
+    pub max_call_depth: usize,
+    pub max_stack: usize,
+}
+
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+/// The default `Budget::max_stack`, which leaves room to spare in the 2MiB stack Rust gives new
+/// threads.
+pub const DEFAULT_MAX_STACK: usize = 1 << 20;
+
+impl Default for Budget {
+    fn default() -> Budget {
+        Budget {
+            max_steps: None,
+            timeout: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_stack: DEFAULT_MAX_STACK,
+        }
+    }
+}
+
+/**
+Why a statement stopped before it was done: either an error, or a `return` making its way back out
+to the call, playing the part of the `Return` exception in
+[10.5.1 Returning from calls](https://craftinginterpreters.com/functions.html#returning-from-calls).
+*/
+enum Unwind {
+    Error(RuntimeError),
+    Return(Value),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Unwind {
+        Unwind::Error(error)
+    }
+}
+
+type Evaluation = Result<Value, RuntimeError>;
+type Execution = Result<(), Unwind>;
+
+/**
+Executes statements and evaluates expressions, following
+[7 Evaluating Expressions](https://craftinginterpreters.com/evaluating-expressions.html),
+[8 Statements and State](https://craftinginterpreters.com/statements-and-state.html) and
+[9 Control Flow](https://craftinginterpreters.com/control-flow.html) and
+[10 Functions](https://craftinginterpreters.com/functions.html).
+
+The global environment lives as long as the interpreter, so running several programs with the same
+interpreter (as the REPL does) lets later ones see the variables defined by earlier ones. Functions
+refer to their bodies' expressions by `ExprId`, so every program run by an interpreter must be parsed
+into the same `Ast`, as `Lox` does. Programs must also be resolved before they are run, with their
+local variables passed to `resolve`; any variable that isn't local is looked up in the globals.
+
+`print` writes to the interpreter's output, which is standard output unless `set_output` replaces it.
+*/
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    /// How many scopes out each local variable use is, from the resolver.
+    locals: HashMap<ExprId, usize>,
+    budget: Budget,
+    steps: u64,
+    deadline: Option<Instant>,
+    depth: usize,
+    /// Where the native stack was when the outermost call in progress was made.
+    stack_base: usize,
+    output: Box<dyn Write>,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::with_budget(Budget::default())
+    }
+
+    pub fn with_budget(budget: Budget) -> Interpreter {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+
+        let mut interpreter = Interpreter {
+            environment: Rc::clone(&globals),
+            globals,
+            locals: HashMap::new(),
+            budget,
+            steps: 0,
+            deadline: None,
+            depth: 0,
+            stack_base: 0,
+            output: Box::new(io::stdout()),
+        };
+        interpreter.reset_budget();
+        interpreter
+    }
+
+    /// Starts spending the budget afresh, from no steps taken and a deadline counted from now.
+    pub fn reset_budget(&mut self) {
+        self.steps = 0;
+        self.deadline = self.budget.timeout.map(|timeout| Instant::now() + timeout);
+    }
+
+    /// Records where the local variables of a program found by the `Resolver` live.
+    pub fn resolve(&mut self, locals: HashMap<ExprId, usize>) {
+        self.locals.extend(locals);
+    }
+
+    /// Sends what `print` writes to `output` from now on.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    pub fn interpret(&mut self, ast: &Ast, statements: &[Stmt]) -> Result<(), RuntimeError> {
+        finish(self.execute_stmts(ast, statements))
+    }
+
+    pub fn execute(&mut self, ast: &Ast, stmt: &Stmt) -> Result<(), RuntimeError> {
+        finish(self.visit_stmt(ast, stmt))
+    }
+
+    pub fn evaluate(&mut self, ast: &Ast, expr: ExprId) -> Evaluation {
+        self.visit_expr(ast, expr)
+    }
+
+    pub fn globals(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.globals)
+    }
+
+    fn execute_stmts(&mut self, ast: &Ast, statements: &[Stmt]) -> Execution {
+        for statement in statements {
+            self.visit_stmt(ast, statement)?;
+        }
+
+        Ok(())
+    }
+
+    /// Takes one step, failing at `token` once the budget has run out.
+    fn step(&mut self, token: &Token) -> Result<(), RuntimeError> {
+        self.steps += 1;
+
+        if let Some(max_steps) = self.budget.max_steps {
+            if self.steps > max_steps {
+                return Err(RuntimeError::exceeded(
+                    token,
+                    Limit::Steps,
+                    &format!("Exceeded the limit of {max_steps} steps."),
+                ));
+            }
+        }
+        if let (Some(deadline), Some(timeout)) = (self.deadline, self.budget.timeout) {
+            if Instant::now() >= deadline {
+                return Err(RuntimeError::exceeded(
+                    token,
+                    Limit::Time,
+                    &format!("Exceeded the time limit of {}ms.", timeout.as_millis()),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute_block(
+        &mut self,
+        ast: &Ast,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Execution {
+        let previous = mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        // The previous environment has to be restored even when a statement fails.
+        let result = self.execute_stmts(ast, statements);
+        self.environment = previous;
+        result
+    }
+
+    fn call_function(
+        &mut self,
+        ast: &Ast,
+        function: &LoxFunction,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Evaluation {
+        let position = stack_position();
+        if self.depth == 0 {
+            self.stack_base = position;
+        }
+        if self.depth >= self.budget.max_call_depth
+            || self.stack_base.abs_diff(position) > self.budget.max_stack
+        {
+            return Err(RuntimeError::new(paren, "Stack overflow."));
+        }
+
+        let mut environment = Environment::with_enclosing(Rc::clone(&function.closure));
+        for (param, argument) in function.declaration.params.iter().zip(arguments) {
+            environment.define(&param.text, argument);
+        }
+
+        self.depth += 1;
+        let result = self.execute_block(ast, &function.declaration.body, environment);
+        self.depth -= 1;
+
+        match result {
+            Ok(()) => Ok(Value::Nil),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(mut error)) => {
+                error.stack.push(Frame {
+                    function: String::from(function.name()),
+                    line: paren.line,
+                });
+                Err(error)
+            }
+        }
+    }
+}
+
+/// The address of a local variable, which tells how far along the native stack the caller is.
+#[inline(never)]
+fn stack_position() -> usize {
+    let marker = 0u8;
+    hint::black_box(&marker) as *const u8 as usize
+}
+
+/// Ends an `Execution` at the top level. A `return` can only unwind this far in a tree that didn't
+/// come from the parser, which rejects `return` outside of functions; it simply stops the program.
+fn finish(result: Execution) -> Result<(), RuntimeError> {
+    match result {
+        Err(Unwind::Error(error)) => Err(error),
+        _ => Ok(()),
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+impl StmtVisitor<Execution> for Interpreter {
+    fn visit_block_stmt(&mut self, ast: &Ast, statements: &[Stmt]) -> Execution {
+        let environment = Environment::with_enclosing(Rc::clone(&self.environment));
+        self.execute_block(ast, statements, environment)
+    }
+
+    fn visit_expression_stmt(&mut self, ast: &Ast, expr: ExprId) -> Execution {
+        self.evaluate(ast, expr)?;
+        Ok(())
+    }
+
+    fn visit_function_stmt(&mut self, _ast: &Ast, declaration: &Rc<FunctionDecl>) -> Execution {
+        let function = LoxFunction {
+            declaration: Rc::clone(declaration),
+            closure: Rc::clone(&self.environment),
+        };
+        self.environment
+            .borrow_mut()
+            .define(&declaration.name.text, Value::Function(Rc::new(function)));
+        Ok(())
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Execution {
+        if self.evaluate(ast, condition)?.is_truthy() {
+            self.visit_stmt(ast, then_branch)
+        } else if let Some(else_branch) = else_branch {
+            self.visit_stmt(ast, else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn visit_print_stmt(&mut self, ast: &Ast, expr: ExprId) -> Execution {
+        let value = self.evaluate(ast, expr)?;
+        // As with a closed pipe, a script carries on when its output can't be written.
+        let _ = writeln!(self.output, "{value}");
+        Ok(())
+    }
+
+    fn visit_return_stmt(
+        &mut self,
+        ast: &Ast,
+        _keyword: &Token,
+        value: Option<ExprId>,
+    ) -> Execution {
+        let value = match value {
+            Some(value) => self.evaluate(ast, value)?,
+            None => Value::Nil,
+        };
+
+        Err(Unwind::Return(value))
+    }
+
+    fn visit_var_stmt(
+        &mut self,
+        ast: &Ast,
+        name: &Token,
+        initializer: Option<ExprId>,
+    ) -> Execution {
+        let value = match initializer {
+            Some(initializer) => self.evaluate(ast, initializer)?,
+            None => Value::Nil,
+        };
+
+        self.environment.borrow_mut().define(&name.text, value);
+        Ok(())
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        ast: &Ast,
+        keyword: &Token,
+        condition: ExprId,
+        body: &Stmt,
+    ) -> Execution {
+        while self.evaluate(ast, condition)?.is_truthy() {
+            self.step(keyword)?;
+            self.visit_stmt(ast, body)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Visitor<Evaluation> for Interpreter {
+    fn visit_assign(&mut self, ast: &Ast, id: ExprId, name: &Token, value: ExprId) -> Evaluation {
+        let value = self.evaluate(ast, value)?;
+        match self.locals.get(&id) {
+            Some(&distance) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(distance, name, value.clone())?
+            }
+            None => self.globals.borrow_mut().assign(name, value.clone())?,
+        }
+        Ok(value)
+    }
+
+    fn visit_binary(
+        &mut self,
+        ast: &Ast,
+        left: ExprId,
+        operator: &Token,
+        right: ExprId,
+    ) -> Evaluation {
+        // The right operand isn't evaluated once the left one has failed, so that e.g. its side
+        // effects don't run after an error or an `exit`.
+        let left = self.evaluate(ast, left)?;
+        let right = self.evaluate(ast, right)?;
+
+        match (&operator.token_type, left, right) {
+            (TokenType::EqualEqual, l, r) => Ok(Value::Boolean(l == r)),
+            (TokenType::BangEqual, l, r) => Ok(Value::Boolean(l != r)),
+            (TokenType::Plus, Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
+            (TokenType::Plus, Value::String(l), Value::String(r)) => {
+                Ok(Value::String(format!("{l}{r}")))
+            }
+            (TokenType::Plus, _, _) => Err(RuntimeError::new(
+                operator,
+                "Operands must be two numbers or two strings.",
+            )),
+            (token_type, Value::Number(l), Value::Number(r)) => match token_type {
+                TokenType::Minus => Ok(Value::Number(l - r)),
+                TokenType::Star => Ok(Value::Number(l * r)),
+                TokenType::Slash => Ok(Value::Number(l / r)),
+                TokenType::Greater => Ok(Value::Boolean(l > r)),
+                TokenType::GreaterEqual => Ok(Value::Boolean(l >= r)),
+                TokenType::Less => Ok(Value::Boolean(l < r)),
+                TokenType::LessEqual => Ok(Value::Boolean(l <= r)),
+                _ => unreachable!("The parser only produces binary arithmetic and comparisons"),
+            },
+            _ => Err(RuntimeError::new(operator, "Operands must be numbers.")),
+        }
+    }
+
+    fn visit_call(
+        &mut self,
+        ast: &Ast,
+        callee: ExprId,
+        paren: &Token,
+        arguments: &[ExprId],
+    ) -> Evaluation {
+        let callee = self.evaluate(ast, callee)?;
+        let mut values = vec![];
+        for argument in arguments {
+            values.push(self.evaluate(ast, *argument)?);
+        }
+
+        self.step(paren)?;
+
+        let arity = match &callee {
+            Value::Native(function) => function.arity,
+            Value::Function(function) => Arity::Fixed(function.arity()),
+            _ => {
+                return Err(RuntimeError::new(
+                    paren,
+                    "Can only call functions and classes.",
+                ))
+            }
+        };
+
+        if let Arity::Fixed(arity) = arity {
+            if values.len() != arity {
+                return Err(RuntimeError::new(
+                    paren,
+                    &format!("Expected {arity} arguments but got {}.", values.len()),
+                ));
+            }
+        }
+
+        match callee {
+            Value::Native(function) => match function.call(&values) {
+                Ok(value) => Ok(value),
+                Err(NativeError::Message(message)) => Err(RuntimeError::new(paren, &message)),
+                Err(NativeError::Exit(code)) => {
+                    // The host may end the process as soon as it gets the status back.
+                    let _ = self.output.flush();
+                    Err(RuntimeError::exit(paren, code))
+                }
+            },
+            Value::Function(function) => self.call_function(ast, &function, paren, values),
+            _ => unreachable!("Only functions have an arity"),
+        }
+    }
+
+    fn visit_comma(&mut self, ast: &Ast, left: ExprId, right: ExprId) -> Evaluation {
+        self.evaluate(ast, left)?;
+        self.evaluate(ast, right)
+    }
+
+    fn visit_grouping(&mut self, ast: &Ast, inner: ExprId) -> Evaluation {
+        walk_grouping(self, ast, inner)
+    }
+
+    fn visit_literal(&mut self, _ast: &Ast, literal: &LiteralType) -> Evaluation {
+        Ok(match literal {
+            LiteralType::Number(n) => Value::Number(*n),
+            LiteralType::String(s) => Value::String(s.clone()),
+            LiteralType::True(t) => Value::Boolean(*t),
+            LiteralType::False(f) => Value::Boolean(*f),
+            LiteralType::Nil(()) => Value::Nil,
+        })
+    }
+
+    fn visit_logical(
+        &mut self,
+        ast: &Ast,
+        left: ExprId,
+        operator: &Token,
+        right: ExprId,
+    ) -> Evaluation {
+        let left = self.evaluate(ast, left)?;
+
+        // The operators return whichever operand decided the result rather than a boolean.
+        let short_circuits = match operator.token_type {
+            TokenType::Or => left.is_truthy(),
+            _ => !left.is_truthy(),
+        };
+        if short_circuits {
+            Ok(left)
+        } else {
+            self.evaluate(ast, right)
+        }
+    }
+
+    fn visit_ternary(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    ) -> Evaluation {
+        // Only the chosen branch is evaluated.
+        if self.evaluate(ast, condition)?.is_truthy() {
+            self.evaluate(ast, then_branch)
+        } else {
+            self.evaluate(ast, else_branch)
+        }
+    }
+
+    fn visit_unary(&mut self, ast: &Ast, operator: &Token, right: ExprId) -> Evaluation {
+        let right = self.evaluate(ast, right)?;
+
+        match (&operator.token_type, right) {
+            (TokenType::Bang, right) => Ok(Value::Boolean(!right.is_truthy())),
+            (TokenType::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
+            _ => Err(RuntimeError::new(operator, "Operand must be a number.")),
+        }
+    }
+
+    fn visit_variable(&mut self, _ast: &Ast, id: ExprId, name: &Token) -> Evaluation {
+        match self.locals.get(&id) {
+            Some(&distance) => self.environment.borrow().get_at(distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Budget, Frame, Interpreter, Limit};
+    use crate::errors;
+    use crate::expr::Ast;
+    use crate::output::Buffer;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+    use crate::token::{Span, Token, TokenType};
+    use crate::value::Value;
+
+    fn evaluate(source: &str) -> Result<Value, String> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let expr = Parser::new(tokens, &mut ast).parse_expression().unwrap();
+
+        Interpreter::new()
+            .evaluate(&ast, expr)
+            .map_err(|e| e.message)
+    }
+
+    fn run(source: &str) -> Result<Interpreter, String> {
+        let mut interpreter = Interpreter::new();
+        run_with(&mut interpreter, source).map_err(|e| e.message)?;
+        Ok(interpreter)
+    }
+
+    fn run_with(interpreter: &mut Interpreter, source: &str) -> Result<(), super::RuntimeError> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+        interpreter.resolve(Resolver::new().resolve(&ast, &statements).unwrap());
+
+        interpreter.interpret(&ast, &statements)
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> Value {
+        let token = Token::new(
+            TokenType::Identifier,
+            String::from(name),
+            String::from(name),
+            1,
+            Span::default(),
+        );
+        interpreter.globals().borrow().get(&token).unwrap()
+    }
+
+    #[test]
+    fn it_scopes_variables_to_blocks() {
+        let interpreter =
+            run("var a = 1; var b = 2; { var a = 10; b = a + b; } a = a + 1;").unwrap();
+
+        assert_eq!(global(&interpreter, "a"), Value::Number(2.0));
+        assert_eq!(global(&interpreter, "b"), Value::Number(12.0));
+    }
+
+    #[test]
+    fn it_prints_to_its_output() {
+        let output = Buffer::new();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(output.clone()));
+
+        run_with(&mut interpreter, "print 1 + 2; print \"a\"; print nil;").unwrap();
+
+        assert_eq!(output.contents(), "3\na\nnil\n");
+    }
+
+    #[test]
+    fn it_reports_undefined_variables() {
+        assert_eq!(
+            run("{ var a = 1; } print a;").err(),
+            Some(String::from("Undefined variable 'a'."))
+        );
+        assert_eq!(
+            run("b = 1;").err(),
+            Some(String::from("Undefined variable 'b'."))
+        );
+    }
+
+    #[test]
+    fn it_evaluates_expressions() {
+        assert_eq!(evaluate("(1 + 2) * 3 - 4 / 2"), Ok(Value::Number(7.0)));
+        assert_eq!(
+            evaluate("\"a\" + \"b\""),
+            Ok(Value::String(String::from("ab")))
+        );
+        assert_eq!(evaluate("!nil == (1 < 2)"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate("1 == \"1\""), Ok(Value::Boolean(false)));
+        assert_eq!(evaluate("nil ? 1 : 2, 3"), Ok(Value::Number(3.0)));
+    }
+
+    #[test]
+    fn it_reports_runtime_errors() {
+        assert_eq!(
+            evaluate("-\"x\""),
+            Err(String::from("Operand must be a number."))
+        );
+        assert_eq!(
+            evaluate("1 + nil"),
+            Err(String::from("Operands must be two numbers or two strings."))
+        );
+        assert_eq!(
+            evaluate("true < 1"),
+            Err(String::from("Operands must be numbers."))
+        );
+    }
+
+    #[test]
+    fn it_stops_evaluating_operands_at_the_first_error() {
+        let output = Buffer::new();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(output.clone()));
+
+        run_with(
+            &mut interpreter,
+            "fun s(x) { print x; return 1; } print s(\"left\") + s(\"right\");",
+        )
+        .unwrap();
+        assert_eq!(output.take(), "left\nright\n2\n");
+
+        let error = run_with(&mut interpreter, "print -nil + s(\"right\");").unwrap_err();
+        assert_eq!(error.message, "Operand must be a number.");
+        assert_eq!(output.contents(), "");
+    }
+
+    #[test]
+    fn it_runs_control_flow() {
+        let interpreter = run("var a = 0; var b = 1; \
+             for (var i = 0; i < 10; i = i + 1) { var t = a; a = b; b = t + b; } \
+             var c; if (a > 50 and nil) c = 1; else if (a or c) c = 2; else c = 3; \
+             var n = 0; while (n < 5) n = n + 2;")
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "a"), Value::Number(55.0));
+        assert_eq!(global(&interpreter, "c"), Value::Number(2.0));
+        assert_eq!(global(&interpreter, "n"), Value::Number(6.0));
+    }
+
+    #[test]
+    fn it_short_circuits_logical_operators() {
+        assert_eq!(
+            evaluate("nil or \"yes\""),
+            Ok(Value::String(String::from("yes")))
+        );
+        assert_eq!(evaluate("0 and false"), Ok(Value::Boolean(false)));
+        // The right operand would fail if it were evaluated.
+        assert_eq!(evaluate("true or -nil"), Ok(Value::Boolean(true)));
+        assert_eq!(evaluate("nil and -nil"), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn it_stops_loops_that_run_out_of_steps() {
+        let mut interpreter = Interpreter::with_budget(Budget {
+            max_steps: Some(100),
+            ..Budget::default()
+        });
+
+        let error =
+            run_with(&mut interpreter, "var i = 0;\nwhile (true) { i = i + 1; }").unwrap_err();
+        assert_eq!(error.limit, Some(Limit::Steps));
+        assert_eq!(error.message, "Exceeded the limit of 100 steps.");
+        assert_eq!(error.token.line, 2);
+        assert_eq!(global(&interpreter, "i"), Value::Number(100.0));
+
+        // Without a reset, the budget stays spent.
+        assert!(run_with(&mut interpreter, "for (;;) {}").is_err());
+        interpreter.reset_budget();
+        assert!(run_with(&mut interpreter, "for (var j = 0; j < 100; j = j + 1) {}").is_ok());
+    }
+
+    #[test]
+    fn it_stops_loops_that_run_out_of_time() {
+        let mut interpreter = Interpreter::with_budget(Budget {
+            timeout: Some(Duration::from_millis(20)),
+            ..Budget::default()
+        });
+
+        let error = run_with(&mut interpreter, "while (true) {}").unwrap_err();
+        assert_eq!(error.limit, Some(Limit::Time));
+        assert_eq!(error.message, "Exceeded the time limit of 20ms.");
+    }
+
+    #[test]
+    fn it_calls_functions() {
+        let interpreter = run(
+            "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); } \
+             fun noop() {} \
+             var a = fib(10); var b = noop();",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "a"), Value::Number(55.0));
+        assert_eq!(global(&interpreter, "b"), Value::Nil);
+        assert_eq!(global(&interpreter, "fib").to_string(), "<fn fib>");
+    }
+
+    #[test]
+    fn it_closes_over_variables() {
+        let interpreter = run(
+            "fun counter() { var i = 0; fun count() { i = i + 1; return i; } return count; } \
+             var c = counter(); c(); var a = c(); var b = counter()();",
+        )
+        .unwrap();
+
+        assert_eq!(global(&interpreter, "a"), Value::Number(2.0));
+        assert_eq!(global(&interpreter, "b"), Value::Number(1.0));
+    }
+
+    #[test]
+    fn it_records_the_calls_an_error_unwinds_through() {
+        let mut interpreter = Interpreter::new();
+
+        let error = run_with(
+            &mut interpreter,
+            "fun inner() {\n  return -nil;\n}\nfun outer() { return inner(); }\nouter();",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.stack,
+            [
+                Frame {
+                    function: String::from("inner"),
+                    line: 4,
+                },
+                Frame {
+                    function: String::from("outer"),
+                    line: 5,
+                },
+            ]
+        );
+        assert_eq!(
+            errors::describe_runtime_error(&error),
+            "Operand must be a number.\n[line 2] in inner()\n[line 4] in outer()\n[line 5] in script"
+        );
+        // The error left the function's scope behind.
+        assert!(run_with(&mut interpreter, "var inner = 1;").is_ok());
+    }
+
+    #[test]
+    fn it_limits_how_deeply_calls_nest() {
+        let mut interpreter = Interpreter::with_budget(Budget {
+            max_call_depth: 50,
+            ..Budget::default()
+        });
+
+        let error = run_with(&mut interpreter, "fun f(n) { return f(n + 1); } f(0);").unwrap_err();
+        assert_eq!(error.message, "Stack overflow.");
+        assert_eq!(error.limit, None);
+        assert_eq!(error.stack.len(), 50);
+        let countdown = |n| format!("fun g(n) {{ if (n > 0) g(n - 1); }} g({n});");
+        assert!(run_with(&mut interpreter, &countdown(49)).is_ok());
+        assert!(run_with(&mut interpreter, &countdown(50)).is_err());
+    }
+
+    #[test]
+    fn it_stops_at_the_first_stack_overflow() {
+        // The step limit only keeps the test short should the right operand recurse again after
+        // the left one overflowed, which would make calls grow exponentially with the depth.
+        let mut interpreter = Interpreter::with_budget(Budget {
+            max_steps: Some(1000),
+            max_call_depth: 50,
+            ..Budget::default()
+        });
+
+        let error = run_with(
+            &mut interpreter,
+            "var calls = 0;\nfun f() {\n  calls = calls + 1;\n  return f() + f();\n}\nf();",
+        )
+        .unwrap_err();
+        assert_eq!(error.message, "Stack overflow.");
+        assert_eq!(error.stack.len(), 50);
+        assert!(error.stack.iter().all(|frame| frame.function == "f"));
+        assert_eq!(error.stack.last().map(|frame| frame.line), Some(6));
+        assert_eq!(global(&interpreter, "calls"), Value::Number(50.0));
+    }
+
+    #[test]
+    fn it_limits_how_much_native_stack_calls_use() {
+        let mut interpreter = Interpreter::with_budget(Budget {
+            max_call_depth: usize::MAX,
+            max_stack: 64 << 10,
+            ..Budget::default()
+        });
+
+        let error = run_with(&mut interpreter, "fun f() { return 1 + f(); } f();").unwrap_err();
+        assert_eq!(error.message, "Stack overflow.");
+        assert!(!error.stack.is_empty());
+    }
+}
@@ -0,0 +1,614 @@
+/*!
+Serializes tokens and expression trees to and from JSON for tools outside of rlox.
+
+Every document is an object carrying the `version` of the schema it was written with alongside its
+payload, e.g. `{ "version": 1, "tokens": [...] }`, `{ "version": 1, "expr": {...} }` or
+`{ "version": 1, "statements": [...] }`. Expressions and statements are written as nested objects
+tagged with their `type`, so the document doesn't depend on the `ExprId`s of the `Ast` it came from.
+The schema version is bumped whenever a change would break existing readers.
+
+JSON has no infinities or `NaN`, which constant folding can produce, e.g. from `1 / 0`. Number
+literals holding them are written as the strings `"inf"`, `"-inf"` and `"NaN"` instead.
+*/
+use std::fmt;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::expr::{Ast, Expr, ExprId, LiteralType};
+use crate::printer::Printer;
+use crate::stmt::{FunctionDecl, Stmt};
+use crate::token::{Span, Token};
+
+pub const SCHEMA_VERSION: u64 = 1;
+
+#[derive(Debug)]
+pub enum JsonError {
+    Malformed(serde_json::Error),
+    UnsupportedVersion(Option<u64>),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::Malformed(e) => write!(f, "Malformed JSON document: {e}"),
+            JsonError::UnsupportedVersion(Some(version)) => write!(
+                f,
+                "Unsupported schema version {version}; expected {SCHEMA_VERSION}."
+            ),
+            JsonError::UnsupportedVersion(None) => write!(f, "Missing schema version."),
+        }
+    }
+}
+
+impl From<serde_json::Error> for JsonError {
+    fn from(e: serde_json::Error) -> JsonError {
+        JsonError::Malformed(e)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TokensDocument {
+    version: u64,
+    tokens: Vec<Token>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ExprDocument {
+    version: u64,
+    expr: Node,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProgramDocument {
+    version: u64,
+    statements: Vec<StmtNode>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Node {
+    #[serde(flatten)]
+    kind: NodeKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    span: Option<Span>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum NodeKind {
+    Assign {
+        name: Token,
+        value: Box<Node>,
+    },
+    Binary {
+        left: Box<Node>,
+        operator: Token,
+        right: Box<Node>,
+    },
+    Call {
+        callee: Box<Node>,
+        paren: Token,
+        arguments: Vec<Node>,
+    },
+    Comma {
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    Grouping {
+        expression: Box<Node>,
+    },
+    Literal {
+        value: LiteralType,
+    },
+    Logical {
+        left: Box<Node>,
+        operator: Token,
+        right: Box<Node>,
+    },
+    Ternary {
+        condition: Box<Node>,
+        then_branch: Box<Node>,
+        else_branch: Box<Node>,
+    },
+    Unary {
+        operator: Token,
+        right: Box<Node>,
+    },
+    Variable {
+        name: Token,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum StmtNode {
+    Block {
+        statements: Vec<StmtNode>,
+    },
+    Expression {
+        expression: Node,
+    },
+    Function {
+        name: Token,
+        params: Vec<Token>,
+        body: Vec<StmtNode>,
+    },
+    If {
+        condition: Node,
+        then_branch: Box<StmtNode>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        else_branch: Option<Box<StmtNode>>,
+    },
+    Print {
+        expression: Node,
+    },
+    Return {
+        keyword: Token,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        value: Option<Node>,
+    },
+    Var {
+        name: Token,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        initializer: Option<Node>,
+    },
+    While {
+        keyword: Token,
+        condition: Node,
+        body: Box<StmtNode>,
+    },
+}
+
+pub fn tokens_to_json(tokens: &[Token]) -> String {
+    let document = TokensDocument {
+        version: SCHEMA_VERSION,
+        tokens: tokens.to_vec(),
+    };
+    serde_json::to_string_pretty(&document).expect("Tokens are always serializable")
+}
+
+pub fn tokens_from_json(json: &str) -> Result<Vec<Token>, JsonError> {
+    let document: TokensDocument = serde_json::from_value(versioned(json)?)?;
+    Ok(document.tokens)
+}
+
+pub fn expr_to_json(ast: &Ast, expr: ExprId) -> String {
+    let document = ExprDocument {
+        version: SCHEMA_VERSION,
+        expr: to_node(ast, expr),
+    };
+    serde_json::to_string_pretty(&document).expect("Expressions are always serializable")
+}
+
+/// Reads an expression written by `expr_to_json`, adding its nodes to `ast`.
+pub fn expr_from_json(json: &str, ast: &mut Ast) -> Result<ExprId, JsonError> {
+    let document: ExprDocument = serde_json::from_value(versioned(json)?)?;
+    Ok(from_node(document.expr, ast))
+}
+
+pub fn program_to_json(ast: &Ast, statements: &[Stmt]) -> String {
+    let document = ProgramDocument {
+        version: SCHEMA_VERSION,
+        statements: statements.iter().map(|s| to_stmt_node(ast, s)).collect(),
+    };
+    serde_json::to_string_pretty(&document).expect("Statements are always serializable")
+}
+
+/// Reads a program written by `program_to_json`, adding the nodes of its expressions to `ast`.
+pub fn program_from_json(json: &str, ast: &mut Ast) -> Result<Vec<Stmt>, JsonError> {
+    let document: ProgramDocument = serde_json::from_value(versioned(json)?)?;
+    Ok(document
+        .statements
+        .into_iter()
+        .map(|node| from_stmt_node(node, ast))
+        .collect())
+}
+
+/// Prints expressions as JSON documents so the CLI can offer JSON as one more notation.
+pub struct JsonPrinter {}
+
+impl Printer for JsonPrinter {
+    fn print(&mut self, ast: &Ast, expr: ExprId) -> String {
+        expr_to_json(ast, expr)
+    }
+
+    fn print_program(&mut self, ast: &Ast, statements: &[Stmt]) -> String {
+        program_to_json(ast, statements)
+    }
+}
+
+// Checks the version before the rest of the document so that a document from a newer schema is
+// reported as such rather than as whatever it no longer has in common with this one.
+fn versioned(json: &str) -> Result<Value, JsonError> {
+    let value: Value = serde_json::from_str(json)?;
+    match value.get("version").and_then(Value::as_u64) {
+        Some(SCHEMA_VERSION) => Ok(value),
+        version => Err(JsonError::UnsupportedVersion(version)),
+    }
+}
+
+fn to_node(ast: &Ast, expr: ExprId) -> Node {
+    let child = |id: &ExprId| Box::new(to_node(ast, *id));
+
+    let kind = match &ast[expr] {
+        Expr::Assign { name, value } => NodeKind::Assign {
+            name: name.clone(),
+            value: child(value),
+        },
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => NodeKind::Binary {
+            left: child(left),
+            operator: operator.clone(),
+            right: child(right),
+        },
+        Expr::Call {
+            callee,
+            paren,
+            arguments,
+        } => NodeKind::Call {
+            callee: child(callee),
+            paren: paren.clone(),
+            arguments: arguments.iter().map(|id| to_node(ast, *id)).collect(),
+        },
+        Expr::Comma { left, right } => NodeKind::Comma {
+            left: child(left),
+            right: child(right),
+        },
+        Expr::Grouping(inner) => NodeKind::Grouping {
+            expression: child(inner),
+        },
+        Expr::Literal(literal) => NodeKind::Literal {
+            value: literal.clone(),
+        },
+        Expr::Logical {
+            left,
+            operator,
+            right,
+        } => NodeKind::Logical {
+            left: child(left),
+            operator: operator.clone(),
+            right: child(right),
+        },
+        Expr::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+        } => NodeKind::Ternary {
+            condition: child(condition),
+            then_branch: child(then_branch),
+            else_branch: child(else_branch),
+        },
+        Expr::Unary { operator, right } => NodeKind::Unary {
+            operator: operator.clone(),
+            right: child(right),
+        },
+        Expr::Variable(name) => NodeKind::Variable { name: name.clone() },
+    };
+
+    Node {
+        kind,
+        span: ast.span(expr),
+    }
+}
+
+fn from_node(node: Node, ast: &mut Ast) -> ExprId {
+    let expr = match node.kind {
+        NodeKind::Assign { name, value } => Expr::Assign {
+            name,
+            value: from_node(*value, ast),
+        },
+        NodeKind::Binary {
+            left,
+            operator,
+            right,
+        } => Expr::Binary {
+            left: from_node(*left, ast),
+            operator,
+            right: from_node(*right, ast),
+        },
+        NodeKind::Call {
+            callee,
+            paren,
+            arguments,
+        } => Expr::Call {
+            callee: from_node(*callee, ast),
+            paren,
+            arguments: arguments
+                .into_iter()
+                .map(|node| from_node(node, ast))
+                .collect(),
+        },
+        NodeKind::Comma { left, right } => Expr::Comma {
+            left: from_node(*left, ast),
+            right: from_node(*right, ast),
+        },
+        NodeKind::Grouping { expression } => Expr::Grouping(from_node(*expression, ast)),
+        NodeKind::Literal { value } => Expr::Literal(value),
+        NodeKind::Logical {
+            left,
+            operator,
+            right,
+        } => Expr::Logical {
+            left: from_node(*left, ast),
+            operator,
+            right: from_node(*right, ast),
+        },
+        NodeKind::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+        } => Expr::Ternary {
+            condition: from_node(*condition, ast),
+            then_branch: from_node(*then_branch, ast),
+            else_branch: from_node(*else_branch, ast),
+        },
+        NodeKind::Unary { operator, right } => Expr::Unary {
+            operator,
+            right: from_node(*right, ast),
+        },
+        NodeKind::Variable { name } => Expr::Variable(name),
+    };
+
+    let id = ast.add(expr);
+    if let Some(span) = node.span {
+        ast.set_span(id, span);
+    }
+    id
+}
+
+fn to_stmt_node(ast: &Ast, stmt: &Stmt) -> StmtNode {
+    match stmt {
+        Stmt::Block(statements) => StmtNode::Block {
+            statements: statements.iter().map(|s| to_stmt_node(ast, s)).collect(),
+        },
+        Stmt::Expression(expr) => StmtNode::Expression {
+            expression: to_node(ast, *expr),
+        },
+        Stmt::Function(declaration) => StmtNode::Function {
+            name: declaration.name.clone(),
+            params: declaration.params.clone(),
+            body: declaration
+                .body
+                .iter()
+                .map(|s| to_stmt_node(ast, s))
+                .collect(),
+        },
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => StmtNode::If {
+            condition: to_node(ast, *condition),
+            then_branch: Box::new(to_stmt_node(ast, then_branch)),
+            else_branch: else_branch
+                .as_ref()
+                .map(|else_branch| Box::new(to_stmt_node(ast, else_branch))),
+        },
+        Stmt::Print(expr) => StmtNode::Print {
+            expression: to_node(ast, *expr),
+        },
+        Stmt::Return { keyword, value } => StmtNode::Return {
+            keyword: keyword.clone(),
+            value: value.map(|value| to_node(ast, value)),
+        },
+        Stmt::Var { name, initializer } => StmtNode::Var {
+            name: name.clone(),
+            initializer: initializer.map(|initializer| to_node(ast, initializer)),
+        },
+        Stmt::While {
+            keyword,
+            condition,
+            body,
+        } => StmtNode::While {
+            keyword: keyword.clone(),
+            condition: to_node(ast, *condition),
+            body: Box::new(to_stmt_node(ast, body)),
+        },
+    }
+}
+
+fn from_stmt_node(node: StmtNode, ast: &mut Ast) -> Stmt {
+    match node {
+        StmtNode::Block { statements } => Stmt::Block(
+            statements
+                .into_iter()
+                .map(|node| from_stmt_node(node, ast))
+                .collect(),
+        ),
+        StmtNode::Expression { expression } => Stmt::Expression(from_node(expression, ast)),
+        StmtNode::Function { name, params, body } => Stmt::Function(Rc::new(FunctionDecl {
+            name,
+            params,
+            body: body
+                .into_iter()
+                .map(|node| from_stmt_node(node, ast))
+                .collect(),
+        })),
+        StmtNode::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => Stmt::If {
+            condition: from_node(condition, ast),
+            then_branch: Box::new(from_stmt_node(*then_branch, ast)),
+            else_branch: else_branch.map(|else_branch| Box::new(from_stmt_node(*else_branch, ast))),
+        },
+        StmtNode::Print { expression } => Stmt::Print(from_node(expression, ast)),
+        StmtNode::Return { keyword, value } => Stmt::Return {
+            keyword,
+            value: value.map(|value| from_node(value, ast)),
+        },
+        StmtNode::Var { name, initializer } => Stmt::Var {
+            name,
+            initializer: initializer.map(|initializer| from_node(initializer, ast)),
+        },
+        StmtNode::While {
+            keyword,
+            condition,
+            body,
+        } => Stmt::While {
+            keyword,
+            condition: from_node(condition, ast),
+            body: Box::new(from_stmt_node(*body, ast)),
+        },
+    }
+}
+
+/// Reads and writes number literals, including those JSON has no numbers for; see the module docs.
+pub(crate) mod number {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(n: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        match *n {
+            n if n.is_finite() => serializer.serialize_f64(n),
+            f64::INFINITY => serializer.serialize_str("inf"),
+            f64::NEG_INFINITY => serializer.serialize_str("-inf"),
+            _ => serializer.serialize_str("NaN"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Number {
+            Finite(f64),
+            Named(String),
+        }
+
+        match Number::deserialize(deserializer)? {
+            Number::Finite(n) => Ok(n),
+            Number::Named(name) => match name.as_str() {
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                "NaN" => Ok(f64::NAN),
+                _ => Err(serde::de::Error::custom(format!(
+                    "expected a number, \"inf\", \"-inf\" or \"NaN\" but got \"{name}\""
+                ))),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        expr_from_json, expr_to_json, program_from_json, program_to_json, tokens_from_json,
+        tokens_to_json, JsonError,
+    };
+    use crate::constant_folder::ConstantFolder;
+    use crate::expr::{Ast, Expr, LiteralType};
+    use crate::parser::Parser;
+    use crate::printer::Printer;
+    use crate::scanner::Scanner;
+    use crate::source_printer::SourcePrinter;
+    use crate::stmt::Stmt;
+    use crate::token::Span;
+
+    const SOURCE: &str = "-(1 + 2.5) * \"a\" == nil ? true, false : !3";
+
+    #[test]
+    fn it_round_trips_tokens() {
+        let mut scanner = Scanner::new(SOURCE);
+        let tokens = scanner.scan_tokens().clone();
+
+        let json = tokens_to_json(&tokens);
+        let decoded = tokens_from_json(&json).unwrap();
+
+        assert_eq!(decoded.len(), tokens.len());
+        assert_eq!(decoded[1].span, Span::new(1, 2));
+        assert_eq!(tokens_to_json(&decoded), json);
+    }
+
+    #[test]
+    fn it_round_trips_parsed_expressions() {
+        let mut scanner = Scanner::new(SOURCE);
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let expr = Parser::new(tokens, &mut ast).parse_expression().unwrap();
+
+        let json = expr_to_json(&ast, expr);
+        let mut decoded_ast = Ast::new();
+        let decoded = expr_from_json(&json, &mut decoded_ast).unwrap();
+
+        assert_eq!(expr_to_json(&decoded_ast, decoded), json);
+        assert_eq!(decoded_ast.span(decoded), Some(Span::new(0, SOURCE.len())));
+        assert_eq!(
+            SourcePrinter {}.print(&decoded_ast, decoded),
+            SourcePrinter {}.print(&ast, expr)
+        );
+    }
+
+    #[test]
+    fn it_round_trips_parsed_programs() {
+        let source = "var a = 1; { var b; print a = b = 2; } fun f(x, y) { return; } f(a, b)();";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        let json = program_to_json(&ast, &statements);
+        let mut decoded_ast = Ast::new();
+        let decoded = program_from_json(&json, &mut decoded_ast).unwrap();
+
+        assert_eq!(program_to_json(&decoded_ast, &decoded), json);
+        assert_eq!(
+            SourcePrinter {}.print_program(&decoded_ast, &decoded),
+            SourcePrinter {}.print_program(&ast, &statements)
+        );
+    }
+
+    #[test]
+    fn it_round_trips_numbers_json_has_no_numbers_for() {
+        let mut scanner = Scanner::new("1 / 0; -1 / 0; 0 / 0;");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+        let statements = ConstantFolder {}.fold_program(&mut ast, statements);
+
+        let json = program_to_json(&ast, &statements);
+        assert!(json.contains(r#""value": "inf""#), "{json}");
+        let mut decoded_ast = Ast::new();
+        let decoded = program_from_json(&json, &mut decoded_ast).unwrap();
+
+        let numbers: Vec<f64> = decoded
+            .iter()
+            .map(|statement| match statement {
+                Stmt::Expression(expr) => match &decoded_ast[*expr] {
+                    Expr::Literal(LiteralType::Number(n)) => *n,
+                    other => panic!("expected a folded number but got {other:?}"),
+                },
+                other => panic!("expected an expression statement but got {other:?}"),
+            })
+            .collect();
+        assert_eq!(numbers[..2], [f64::INFINITY, f64::NEG_INFINITY]);
+        assert!(numbers[2].is_nan());
+        assert_eq!(program_to_json(&decoded_ast, &decoded), json);
+
+        let mut ast = Ast::new();
+        let number = r#"{ "type": "Number", "value": "many" }"#;
+        let json =
+            format!(r#"{{ "version": 1, "expr": {{ "type": "Literal", "value": {number} }} }}"#);
+        assert!(matches!(
+            expr_from_json(&json, &mut ast),
+            Err(JsonError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn it_rejects_other_schema_versions() {
+        let json = r#"{ "version": 2, "tokens": [] }"#;
+
+        assert!(matches!(
+            tokens_from_json(json),
+            Err(JsonError::UnsupportedVersion(Some(2)))
+        ));
+        assert!(matches!(
+            tokens_from_json(r#"{ "tokens": [] }"#),
+            Err(JsonError::UnsupportedVersion(None))
+        ));
+    }
+}
@@ -0,0 +1,41 @@
+/*!
+A tree-walking interpreter for Lox, following [Crafting Interpreters](https://craftinginterpreters.com/).
+
+Embedders will usually only need `Lox` and `Value`; the scanner, parser, syntax tree and printers
+are public as well for tools that work with Lox source without running it.
+*/
+
+pub mod ast_printer;
+pub mod callable;
+pub mod constant_folder;
+pub mod convert;
+pub mod dot_printer;
+pub mod engine;
+pub mod environment;
+pub mod errors;
+pub mod exits;
+pub mod expr;
+pub mod fold;
+pub mod interpreter;
+pub mod json;
+mod line_editor;
+pub mod lox;
+pub mod natives;
+pub mod output;
+pub mod parser;
+pub mod printer;
+pub mod resolver;
+pub mod rpn_printer;
+pub mod scanner;
+pub mod source_printer;
+pub mod stmt;
+pub mod token;
+pub mod value;
+pub mod visitor;
+
+pub use callable::{Arity, NativeError, NativeResult};
+pub use convert::{ConversionError, FromLox, IntoLox};
+pub use engine::{Config, Lox, LoxError};
+pub use interpreter::Limit;
+pub use natives::Capabilities;
+pub use value::Value;
@@ -0,0 +1,97 @@
+use std::env;
+use std::path::PathBuf;
+
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+
+use crate::scanner::KEYWORDS;
+
+/// Where the REPL keeps its history between sessions, or `None` if there's no home directory.
+pub fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".rlox_history"))
+}
+
+/**
+Tab-completes the word before the cursor with a keyword or the name of a global variable.
+
+The editor owns its helper, so the REPL hands it the names of the globals before reading each line
+rather than the helper looking them up in the session.
+*/
+#[derive(Default)]
+pub struct LoxHelper {
+    pub globals: Vec<String>,
+}
+
+impl LoxHelper {
+    /// Returns where the word being completed starts in `line`, and the names it could become,
+    /// sorted and without duplicates.
+    pub fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let start = line[..pos]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+            .last()
+            .map_or(pos, |(i, _)| i);
+        let word = &line[start..pos];
+        if word.is_empty() {
+            return (pos, vec![]);
+        }
+
+        let mut candidates: Vec<String> = KEYWORDS
+            .iter()
+            .map(|(keyword, _)| *keyword)
+            .chain(self.globals.iter().map(String::as_str))
+            .filter(|name| name.starts_with(word))
+            .map(String::from)
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        (start, candidates)
+    }
+}
+
+impl Completer for LoxHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for LoxHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LoxHelper {}
+
+impl Validator for LoxHelper {}
+
+impl Helper for LoxHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::LoxHelper;
+
+    #[test]
+    fn it_completes_keywords_and_globals() {
+        let helper = LoxHelper {
+            globals: vec![String::from("value"), String::from("total")],
+        };
+
+        assert_eq!(
+            helper.candidates("print va", 8),
+            (6, vec![String::from("value"), String::from("var")])
+        );
+        assert_eq!(helper.candidates("1 + t", 5).1, ["this", "total", "true"]);
+        assert_eq!(helper.candidates("1 + ", 4), (4, vec![]));
+    }
+}
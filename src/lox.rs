@@ -1,42 +1,607 @@
-#![allow(dead_code)]
-
 use std::fs;
-use std::io::{self, Write};
+use std::io;
+use std::time::{Duration, Instant};
+
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
 
+use crate::engine::{Config, Lox, LoxError};
+use crate::errors;
+use crate::exits;
+use crate::expr::Ast;
+use crate::json;
+use crate::line_editor::{self, LoxHelper};
+use crate::natives::Capabilities;
+use crate::parser::Parser;
+use crate::printer::Notation;
 use crate::scanner::Scanner;
-// use crate::exits;
+use crate::value::Value;
+
+/// What to do with a chunk of source code; each one is a subcommand of the CLI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Execute the source.
+    Run,
+    /// Print the tokens the source scans to.
+    Tokens,
+    /// Print the syntax tree the source parses to.
+    Ast,
+    /// Scan, parse and resolve the source, reporting any errors, without running it.
+    Check,
+}
+
+impl Command {
+    pub fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "run" => Some(Command::Run),
+            "tokens" => Some(Command::Tokens),
+            "ast" => Some(Command::Ast),
+            "check" => Some(Command::Check),
+            _ => None,
+        }
+    }
+}
 
-pub fn run_file(path: &String) {
-    let contents = fs::read_to_string(path).expect("Failed to read file");
-    run(&contents);
+/// Settings chosen on the command line that apply to every chunk of source that is run.
+#[derive(Clone)]
+pub struct Options {
+    /// Fold constant expressions before anything else sees the tree (`-O`).
+    pub optimize: bool,
+    /// The notation syntax trees are printed in (`--format`).
+    pub notation: Notation,
+    /// Print tokens as JSON rather than one per line (`--json`).
+    pub json: bool,
+    /// Stop scripts after this many loop iterations and calls (`--max-steps`).
+    pub max_steps: Option<u64>,
+    /// Stop scripts that run for longer than this (`--timeout`).
+    pub timeout: Option<Duration>,
+    /// Only give scripts natives without effects outside of the script (`--sandbox`).
+    pub sandbox: bool,
+    /// Where scripts' random numbers start from (`--seed`).
+    pub seed: u64,
+    /// The arguments passed to the script after its path or `--`, which it gets from `args()`.
+    pub args: Vec<String>,
 }
 
-pub fn run_prompt() {
-    let mut line = String::new();
+impl Options {
+    /// The settings for the `Lox` engine that runs the source.
+    pub fn config(&self) -> Config {
+        Config {
+            optimize: self.optimize,
+            max_steps: self.max_steps,
+            timeout: self.timeout,
+            capabilities: if self.sandbox {
+                Capabilities::none()
+            } else {
+                Capabilities::all()
+            },
+            seed: self.seed,
+            args: self.args.clone(),
+            ..Config::default()
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            optimize: false,
+            notation: Notation::Sexpr,
+            json: false,
+            max_steps: None,
+            timeout: None,
+            sandbox: false,
+            seed: 0,
+            args: vec![],
+        }
+    }
+}
+
+/**
+Reads and runs source from stdin a line at a time, with line editing, tab completion of keywords and
+globals, and history kept in `~/.rlox_history`.
+
+When a line leaves the source incomplete, e.g. with an unclosed parenthesis or string, the REPL shows
+a `...` prompt and keeps reading until the source is complete. Entering a blank line at the `...`
+prompt runs what has been entered so far regardless, reporting whatever errors it has.
+
+Everything entered runs in the same `Session`, so variables defined on one line can be used on the
+next. A line starting with `:` is a `MetaCommand` rather than Lox; `:help` lists them.
+
+Returns the process exit code the REPL should end with: the status passed to `exit` if the input
+called it, or `EX_OK` at the end of the input.
+*/
+pub fn run_prompt(options: &Options) -> i32 {
+    let mut editor: Editor<LoxHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("Could not start the line editor: {e}");
+            return exits::EX_SOFTWARE;
+        }
+    };
+    editor.set_helper(Some(LoxHelper::default()));
+    let history = line_editor::history_path();
+    if let Some(path) = &history {
+        // There's no history to load the first time the REPL is run.
+        let _ = editor.load_history(path);
+    }
+
+    let mut session = Session::new(options.clone());
+    let mut source = String::new();
 
     loop {
-        print!("> ");
-        // https://stackoverflow.com/a/54263074
-        let _ = io::stdout().flush();
-        // https://doc.rust-lang.org/std/io/trait.BufRead.html#method.read_line
-        let num_bytes = io::stdin()
-            .read_line(&mut line)
-            .expect("Reading from stdin won't fail");
-
-        match num_bytes {
-            0 => break,
-            _ => run(&line.clone()),
+        if let Some(helper) = editor.helper_mut() {
+            helper.globals = session.global_names();
+        }
+
+        let line = match editor.readline(if source.is_empty() { "> " } else { "... " }) {
+            Ok(line) => line,
+            // Ctrl-C throws away what has been entered so far rather than ending the session.
+            Err(ReadlineError::Interrupted) => {
+                source.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("Could not read input: {e}");
+                break;
+            }
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+
+        let force = !source.is_empty() && line.trim().is_empty();
+        source.push_str(&line);
+        source.push('\n');
+
+        if source.trim().is_empty() {
+            source.clear();
+            continue;
+        }
+        if source.trim_start().starts_with(':') {
+            match MetaCommand::parse(source.trim()) {
+                Ok(command) => {
+                    session.meta(command);
+                }
+                Err(message) => eprintln!("{message}"),
+            }
+            source.clear();
+            continue;
+        }
+        if !force && is_incomplete(&source) {
+            continue;
+        }
+
+        // An error in one chunk shouldn't end the session, so the exit code is ignored.
+        session.eval(&source);
+        source.clear();
+
+        if session.exited().is_some() {
+            break;
+        }
+    }
+
+    if let Some(path) = &history {
+        if let Err(e) = editor.save_history(path) {
+            eprintln!("Could not save history to {}: {e}", path.display());
+        }
+    }
+
+    session.exited().unwrap_or(exits::EX_OK)
+}
+
+/**
+The state a REPL keeps between chunks of input: a `Lox` engine, and so one global environment, along
+with the options the REPL was started with.
+
+Following the challenges at the end of
+[8 Statements and State](https://craftinginterpreters.com/statements-and-state.html#challenges),
+the value of each expression statement is printed, and it is also bound to `_` so it can be used on
+the next line.
+*/
+pub struct Session {
+    lox: Lox,
+    options: Options,
+    /// The names of the natives and constants every session starts with, sorted.
+    builtins: Vec<String>,
+    /// The status passed to `exit`, once the input has called it.
+    exited: Option<i32>,
+}
+
+impl Session {
+    pub fn new(options: Options) -> Session {
+        let lox = Lox::with_config(options.config());
+        let builtins = lox.globals().into_iter().map(|(name, _)| name).collect();
+        Session {
+            lox,
+            options,
+            builtins,
+            exited: None,
+        }
+    }
+
+    /// The status the input asked to end the session with by calling `exit`, if it has.
+    pub fn exited(&self) -> Option<i32> {
+        self.exited
+    }
+
+    /// Runs a chunk of REPL input, returning the process exit code from `exits` that running it as
+    /// a script would have ended with.
+    pub fn eval(&mut self, source: &str) -> i32 {
+        let statements = match self.lox.parse_repl(source) {
+            Ok(statements) => statements,
+            Err(error) => return report(&mut self.lox, error),
         };
 
-        line.clear();
+        // The limits apply to each chunk of input as a whole, as they would to a script.
+        self.lox.reset_budget();
+        for statement in &statements {
+            match self.lox.execute(statement) {
+                Ok(Some(value)) => {
+                    println!("{value}");
+                    self.lox.define("_", value);
+                }
+                Ok(None) => {}
+                Err(error) => return self.report(error),
+            }
+        }
+
+        exits::EX_OK
+    }
+
+    /// Runs `source` as a script in this session, so whatever it defines can be used afterwards.
+    /// Unlike `eval`, the values of its expression statements aren't printed.
+    pub fn load(&mut self, source: &str) -> i32 {
+        match self.lox.run(source) {
+            Ok(()) => exits::EX_OK,
+            Err(error) => self.report(error),
+        }
+    }
+
+    fn report(&mut self, error: LoxError) -> i32 {
+        if let LoxError::Exit(code) = error {
+            self.exited = Some(code);
+        }
+        report(&mut self.lox, error)
     }
+
+    /// Carries out a meta-command, returning an exit code like `eval` does.
+    pub fn meta(&mut self, command: MetaCommand) -> i32 {
+        match command {
+            MetaCommand::Tokens(code) => print_tokens(code, &self.options),
+            MetaCommand::Ast(code) => {
+                // The tree is never run, so it's parsed by a scratch engine.
+                let mut lox = Lox::with_config(self.options.config());
+                match lox.parse_repl(code) {
+                    Ok(statements) => {
+                        let mut printer = self.options.notation.printer();
+                        println!("{}", printer.print_program(lox.ast(), &statements));
+                        exits::EX_OK
+                    }
+                    Err(error) => report(&mut lox, error),
+                }
+            }
+            MetaCommand::Env => {
+                for (name, value) in self.user_globals() {
+                    println!("{name} = {value}");
+                }
+                exits::EX_OK
+            }
+            MetaCommand::Load(path) => match fs::read_to_string(path) {
+                Ok(source) => self.load(&source),
+                Err(e) => {
+                    eprintln!("Could not read {path}: {e}");
+                    exits::EX_NOINPUT
+                }
+            },
+            MetaCommand::Reset => {
+                *self = Session::new(self.options.clone());
+                exits::EX_OK
+            }
+            MetaCommand::Time(code) => {
+                let start = Instant::now();
+                let code = self.eval(code);
+                println!("Took {:?}.", start.elapsed());
+                code
+            }
+            MetaCommand::Help => {
+                println!("{META_HELP}");
+                exits::EX_OK
+            }
+        }
+    }
+
+    pub fn lox(&self) -> &Lox {
+        &self.lox
+    }
+
+    /// The global variables the input has defined and their values, sorted by name. The natives and
+    /// constants every session starts with would drown them out, so they're left out.
+    pub fn user_globals(&self) -> Vec<(String, Value)> {
+        self.lox
+            .globals()
+            .into_iter()
+            .filter(|(name, _)| self.builtins.binary_search(name).is_err())
+            .collect()
+    }
+
+    /// The names of the global variables defined so far, sorted.
+    pub fn global_names(&self) -> Vec<String> {
+        self.lox
+            .globals()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+const META_HELP: &str = ":tokens <code>  Print the tokens <code> scans to
+:ast <code>     Print the syntax tree <code> parses to
+:env            List the global variables and their values
+:load <file>    Run <file> in this session
+:reset          Start a new session, forgetting every variable
+:time <code>    Run <code> and print how long it took
+:help           Print this list";
+
+/// A command entered at the REPL prompt, starting with `:`, that inspects or changes the session
+/// rather than being run as Lox.
+#[derive(Debug, PartialEq)]
+pub enum MetaCommand<'a> {
+    Tokens(&'a str),
+    Ast(&'a str),
+    Env,
+    Load(&'a str),
+    Reset,
+    Time(&'a str),
+    Help,
 }
 
-fn run(source: &str) {
+impl MetaCommand<'_> {
+    /// Parses a line such as `:ast 1 + 2`, returning a message to show the user when it isn't a
+    /// meta-command or is missing its argument.
+    pub fn parse(line: &str) -> Result<MetaCommand<'_>, String> {
+        let (name, argument) = match line.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (line, ""),
+        };
+
+        let command = match name {
+            ":tokens" => MetaCommand::Tokens(argument),
+            ":ast" => MetaCommand::Ast(argument),
+            ":env" => MetaCommand::Env,
+            ":load" => MetaCommand::Load(argument),
+            ":reset" => MetaCommand::Reset,
+            ":time" => MetaCommand::Time(argument),
+            ":help" => MetaCommand::Help,
+            _ => return Err(format!("Unknown command '{name}'. Enter :help for a list.")),
+        };
+
+        match command {
+            MetaCommand::Load("") => Err(String::from("Usage: :load <file>")),
+            MetaCommand::Time("") => Err(String::from("Usage: :time <code>")),
+            command => Ok(command),
+        }
+    }
+}
+
+/// Whether `source` only fails to scan or parse because it ends too soon.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens().clone();
+    if scanner.errors().iter().any(|e| e.unexpected_eof) {
+        return true;
+    }
+
+    let mut ast = Ast::new();
+    match Parser::new(tokens, &mut ast).parse_repl() {
+        Ok(_) => false,
+        Err(errors) => errors.iter().any(|e| e.unexpected_eof),
+    }
+}
+
+/// Carries out `command` on `source`, returning the process exit code from `exits` that it should
+/// end with.
+pub fn run(command: Command, source: &str, options: &Options) -> i32 {
+    if command == Command::Tokens {
+        return print_tokens(source, options);
+    }
+
+    let mut lox = Lox::with_config(options.config());
+    let statements = match lox.parse(source) {
+        Ok(statements) => statements,
+        Err(error) => return report(&mut lox, error),
+    };
+
+    match command {
+        Command::Ast => println!(
+            "{}",
+            options
+                .notation
+                .printer()
+                .print_program(lox.ast(), &statements)
+        ),
+        Command::Run => {
+            if let Err(error) = lox.interpret(&statements) {
+                return report(&mut lox, error);
+            }
+        }
+        Command::Tokens | Command::Check => {}
+    }
+
+    exits::EX_OK
+}
+
+/// Reports `error` to the diagnostics of `lox` and returns the exit code it calls for.
+fn report(lox: &mut Lox, error: LoxError) -> i32 {
+    lox.report(&error);
+    error.exit_code()
+}
+
+/// Prints the tokens `source` scans to, reporting any scan errors, and returns the exit code.
+fn print_tokens(source: &str, options: &Options) -> i32 {
     let mut scanner = Scanner::new(source);
-    let tokens = scanner.scan_tokens();
+    let tokens = scanner.scan_tokens().clone();
+    for error in scanner.errors() {
+        errors::handle(&mut io::stderr(), error.line, error.message.clone());
+    }
+
+    if options.json {
+        println!("{}", json::tokens_to_json(&tokens));
+    } else {
+        for token in &tokens {
+            println!("{:?}", token);
+        }
+    }
+
+    if scanner.had_error() {
+        exits::EX_DATAERR
+    } else {
+        exits::EX_OK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_incomplete, run, Command, MetaCommand, Options, Session};
+    use crate::exits;
+    use crate::value::Value;
+
+    #[test]
+    fn it_detects_incomplete_input() {
+        assert!(is_incomplete("(1 +\n"));
+        assert!(is_incomplete("\"unterminated\n"));
+        assert!(is_incomplete("true ? 1"));
+        assert!(is_incomplete("{ var a = 1;\n"));
+        assert!(!is_incomplete("(1 + 2)\n"));
+        assert!(!is_incomplete("(1 + 2 3"));
+        assert!(!is_incomplete("== 1"));
+    }
+
+    #[test]
+    fn it_keeps_state_between_chunks() {
+        let mut session = Session::new(Options::default());
+
+        assert_eq!(session.eval("var a = 1;"), exits::EX_OK);
+        assert_eq!(session.eval("a = a + 1;"), exits::EX_OK);
+        assert_eq!(session.lox().get("a"), Some(Value::Number(2.0)));
+        assert_eq!(session.lox().get("_"), Some(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn it_binds_the_last_expression_value() {
+        let mut session = Session::new(Options::default());
+
+        // The last expression statement doesn't need its `;` in the REPL.
+        assert_eq!(session.eval("1 + 2"), exits::EX_OK);
+        assert_eq!(session.eval("_ * 2"), exits::EX_OK);
+        assert_eq!(session.lox().get("_"), Some(Value::Number(6.0)));
+
+        // A failing chunk leaves the previous value in place.
+        assert_eq!(session.eval("-\"a\""), exits::EX_SOFTWARE);
+        assert_eq!(session.lox().get("_"), Some(Value::Number(6.0)));
+    }
+
+    #[test]
+    fn it_parses_meta_commands() {
+        assert_eq!(
+            MetaCommand::parse(":ast  1 + 2 "),
+            Ok(MetaCommand::Ast("1 + 2"))
+        );
+        assert_eq!(MetaCommand::parse(":env"), Ok(MetaCommand::Env));
+        assert!(MetaCommand::parse(":load").is_err());
+        assert!(MetaCommand::parse(":quux").is_err());
+    }
+
+    #[test]
+    fn it_loads_files_into_the_session() {
+        let path = std::env::temp_dir().join("rlox_it_loads_files_into_the_session.lox");
+        std::fs::write(&path, "var a = 1; a + 1;").unwrap();
+        let mut session = Session::new(Options::default());
+
+        let code = session.meta(MetaCommand::Load(path.to_str().unwrap()));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(code, exits::EX_OK);
+        assert_eq!(session.lox().get("a"), Some(Value::Number(1.0)));
+        // Loading a file doesn't echo its expressions, so `_` isn't bound.
+        assert_eq!(session.lox().get("_"), None);
+
+        assert_eq!(session.meta(MetaCommand::Reset), exits::EX_OK);
+        assert_eq!(session.lox().get("a"), None);
+    }
+
+    #[test]
+    fn it_lists_only_the_globals_the_input_defined() {
+        let mut session = Session::new(Options::default());
+        assert_eq!(session.user_globals(), []);
+
+        session.eval("var b = 2; var a = 1;");
+        assert_eq!(
+            session.user_globals(),
+            [
+                (String::from("a"), Value::Number(1.0)),
+                (String::from("b"), Value::Number(2.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_leaves_out_capabilities_in_the_sandbox() {
+        let session = Session::new(Options::default());
+        assert!(session.lox().get("clock").is_some());
+
+        let session = Session::new(Options {
+            sandbox: true,
+            ..Options::default()
+        });
+        assert_eq!(session.lox().get("clock"), None);
+    }
+
+    #[test]
+    fn it_checks_scripts_without_running_them() {
+        let options = Options::default();
+
+        assert_eq!(run(Command::Check, "print -nil;", &options), exits::EX_OK);
+        assert_eq!(
+            run(Command::Check, "print (1;", &options),
+            exits::EX_DATAERR
+        );
+        assert_eq!(
+            run(Command::Check, "{ var a = 1; var a = a; }", &options),
+            exits::EX_DATAERR
+        );
+    }
+
+    #[test]
+    fn it_limits_each_chunk_of_input_as_a_whole() {
+        let mut session = Session::new(Options {
+            max_steps: Some(15),
+            ..Options::default()
+        });
+        let chunk = "var i = 0; while (i < 10) i = i + 1; var j = 0; while (j < 10) j = j + 1;";
+
+        assert_eq!(
+            session.eval("var k = 0; while (k < 10) k = k + 1;"),
+            exits::EX_OK
+        );
+        assert_eq!(
+            session.eval("k = 0; while (k < 10) k = k + 1;"),
+            exits::EX_OK
+        );
+        assert_eq!(session.eval(chunk), exits::EX_TEMPFAIL);
+    }
+
+    #[test]
+    fn it_ends_with_the_status_passed_to_exit() {
+        let options = Options::default();
+        assert_eq!(run(Command::Run, "exit(3); print 1;", &options), 3);
 
-    for token in tokens {
-        println!("{:?}", token);
+        let mut session = Session::new(options);
+        assert_eq!(session.eval("var a = 1;"), exits::EX_OK);
+        assert_eq!(session.exited(), None);
+        assert_eq!(session.eval("exit(0);"), exits::EX_OK);
+        assert_eq!(session.exited(), Some(0));
     }
 }
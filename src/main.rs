@@ -1,25 +1,122 @@
 use std::env;
+use std::fs;
+use std::io::{self, Read};
 use std::process;
+use std::time::Duration;
 
-mod ast_printer;
-mod errors;
-mod exits;
-mod expr;
-mod lox;
-mod parser;
-mod scanner;
-mod token;
-mod visitor;
+use rlox::exits;
+use rlox::lox::{self, Command};
+use rlox::printer::Notation;
+
+const USAGE: &str = "Usage: rlox [command] [options] [script | - | -e code] [--] [args...]
+
+Commands:
+    run       Run the script (the default)
+    tokens    Print the tokens the script scans to
+    ast       Print the syntax tree the script parses to
+    check     Report errors in the script without running it
+
+Options:
+    -e <code>          Use <code> as the script
+    -O                 Fold constant expressions first
+    --format <format>  Print syntax trees as sexpr, rpn, source, dot or json
+    --json             Print tokens or syntax trees as JSON
+    --max-steps <n>    Stop the script after <n> loop iterations and calls
+    --timeout <ms>     Stop the script after <ms> milliseconds
+    --sandbox          Only give the script functions without side effects
+    --seed <n>         Start random() and random_int() from <n>
+
+Reads the script from stdin when it is '-'. Starts a REPL when no script is given.
+Everything after the script, or after '--', is passed to it as args().
+A script stopped by --max-steps or --timeout exits with status 75, and one that
+calls exit(code) exits with status code.";
+
+/// Where the source code to run comes from.
+enum Input {
+    File(String),
+    Stdin,
+    Inline(String),
+}
 
 fn main() {
-    let args: Vec<String> = env::args().skip(1).collect();
-
-    if args.len() > 1 {
-        eprintln!("Usage: rlox [script]");
-        process::exit(exits::EX_USAGE);
-    } else if args.len() == 1 {
-        lox::run_file(&args[0]);
-    } else {
-        lox::run_prompt();
+    let mut args = env::args().skip(1).peekable();
+    let mut options = lox::Options::default();
+    let mut command = None;
+    let mut input = None;
+
+    if let Some(c) = args.peek().and_then(|arg| Command::from_name(arg)) {
+        command = Some(c);
+        args.next();
     }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            "-O" => options.optimize = true,
+            "--sandbox" => options.sandbox = true,
+            "--json" => {
+                options.json = true;
+                options.notation = Notation::Json;
+            }
+            "--format" => match args.next().as_deref().and_then(Notation::from_name) {
+                Some(notation) => options.notation = notation,
+                None => usage(),
+            },
+            "--max-steps" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(max_steps) => options.max_steps = Some(max_steps),
+                None => usage(),
+            },
+            "--seed" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(seed) => options.seed = seed,
+                None => usage(),
+            },
+            "--timeout" => match args.next().and_then(|ms| ms.parse().ok()) {
+                Some(ms) => options.timeout = Some(Duration::from_millis(ms)),
+                None => usage(),
+            },
+            "-e" => match (args.next(), &input) {
+                (Some(code), None) => input = Some(Input::Inline(code)),
+                _ => usage(),
+            },
+            "-" if input.is_none() => {
+                input = Some(Input::Stdin);
+                options.args.extend(args.by_ref());
+            }
+            "--" => options.args.extend(args.by_ref()),
+            _ if input.is_none() && !arg.starts_with('-') => {
+                input = Some(Input::File(arg));
+                options.args.extend(args.by_ref());
+            }
+            _ => usage(),
+        }
+    }
+
+    let command = command.unwrap_or(Command::Run);
+    let source = match input {
+        Some(Input::File(path)) => fs::read_to_string(&path).unwrap_or_else(|e| {
+            eprintln!("Could not read {path}: {e}");
+            process::exit(exits::EX_NOINPUT);
+        }),
+        Some(Input::Stdin) => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source).unwrap_or_else(|e| {
+                eprintln!("Could not read stdin: {e}");
+                process::exit(exits::EX_NOINPUT);
+            });
+            source
+        }
+        Some(Input::Inline(code)) => code,
+        None if command == Command::Run => process::exit(lox::run_prompt(&options)),
+        None => usage(),
+    };
+
+    process::exit(lox::run(command, &source, &options));
+}
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    process::exit(exits::EX_USAGE);
 }
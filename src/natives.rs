@@ -0,0 +1,335 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::engine::{Config, Lox};
+use crate::value::Value;
+
+mod io;
+mod math;
+
+/**
+What the natives installed in a `Lox` engine's global environment may reach outside of the script.
+
+Natives that only compute a result from their arguments are always installed. Each of the others
+needs the capability for what it touches, so a host running scripts it doesn't trust can leave out
+everything that reads or changes the world around them:
+
+```
+use rlox::{Capabilities, Config, Lox};
+
+let mut lox = Lox::with_config(Config {
+    capabilities: Capabilities::none(),
+    ..Config::default()
+});
+assert!(lox.get("clock").is_none());
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Reading the time, e.g. `clock`.
+    pub clock: bool,
+    /// The process around the script: environment variables, command-line arguments and exiting,
+    /// e.g. `env`, `args` and `exit`.
+    pub env: bool,
+    /// Reading files, e.g. `read_file` and `file_exists`.
+    pub fs_read: bool,
+    /// Creating and changing files, e.g. `write_file` and `append_file`.
+    pub fs_write: bool,
+    /// Using the console directly, e.g. reading standard input with `read_line`. `print` doesn't need
+    /// this, since it writes to the engine's output, which the host chooses.
+    pub stdout: bool,
+}
+
+impl Capabilities {
+    /// Every capability, for scripts that are trusted as much as the host itself.
+    pub fn all() -> Capabilities {
+        Capabilities {
+            clock: true,
+            env: true,
+            fs_read: true,
+            fs_write: true,
+            stdout: true,
+        }
+    }
+
+    /// No capabilities, leaving only the natives that compute a result from their arguments.
+    pub fn none() -> Capabilities {
+        Capabilities {
+            clock: false,
+            env: false,
+            fs_read: false,
+            fs_write: false,
+            stdout: false,
+        }
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Capabilities {
+        Capabilities::none()
+    }
+}
+
+/**
+Defines the natives `config` allows as globals of `lox`. Those that need no capabilities are:
+
+- `str(x)`, the text `print` shows for `x`, and `num(s)`, the number `s` spells out
+- `type(x)`, the name of the type of `x`, e.g. "number"
+- `len(x)`, the number of characters in a string or items in a list or map
+- `substr(s, start, end)` and `index_of(s, part)`, counting characters from 0
+- `upper(s)`, `lower(s)` and `trim(s)`
+- `split(s, separator)`, a list of the parts of `s`, or of its characters if `separator` is empty
+- `chr(code)` and `ord(c)`, between a one-character string and its Unicode code point
+- the math natives and constants, with random numbers starting from the seed; see `math::install`
+
+`clock` needs the `clock` capability, and the natives for files and the process need the others; see
+`io::install`.
+*/
+pub fn install(lox: &mut Lox, config: &Config) {
+    lox.define_function("str", |x: Value| x.to_string());
+    lox.define_function("num", num);
+    lox.define_function("type", |x: Value| x.type_name());
+    lox.define_function("len", len);
+    lox.define_function("substr", substr);
+    lox.define_function("index_of", index_of);
+    lox.define_function("upper", |s: String| s.to_uppercase());
+    lox.define_function("lower", |s: String| s.to_lowercase());
+    lox.define_function("trim", |s: String| String::from(s.trim()));
+    lox.define_function("split", split);
+    lox.define_function("chr", chr);
+    lox.define_function("ord", ord);
+    math::install(lox, config.seed);
+
+    if config.capabilities.clock {
+        lox.define_function("clock", clock);
+    }
+    io::install(lox, &config.capabilities, &config.args);
+}
+
+// Numbers are written the way they are in Lox source, optionally negative and surrounded by
+// whitespace, so e.g. "1e3" and "inf" aren't numbers even though Rust would parse them.
+fn num(x: Value) -> Result<f64, String> {
+    let text = match x {
+        Value::Number(n) => return Ok(n),
+        Value::String(text) => text,
+        other => return Err(format!("Expected a string but got {}.", other.type_name())),
+    };
+
+    let digits = text.trim();
+    let digits = digits.strip_prefix('-').unwrap_or(digits);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+    let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    match text.trim().parse() {
+        Ok(n) if is_digits(whole) && is_digits(fraction) => Ok(n),
+        _ => Err(format!("Could not convert '{text}' to a number.")),
+    }
+}
+
+fn len(x: Value) -> Result<usize, String> {
+    match x {
+        Value::String(s) => Ok(s.chars().count()),
+        Value::List(items) => Ok(items.len()),
+        Value::Map(entries) => Ok(entries.len()),
+        other => Err(format!(
+            "Expected a string, list or map but got {}.",
+            other.type_name()
+        )),
+    }
+}
+
+fn substr(s: String, start: usize, end: usize) -> Result<String, String> {
+    let length = s.chars().count();
+    if start > end || end > length {
+        return Err(format!(
+            "Substring {start} to {end} is out of range for a string of length {length}."
+        ));
+    }
+
+    Ok(s.chars().skip(start).take(end - start).collect())
+}
+
+// The index is of the first character of the first occurrence, or `nil` if there is none.
+fn index_of(s: String, part: String) -> Option<usize> {
+    s.find(&part).map(|byte| s[..byte].chars().count())
+}
+
+fn split(s: String, separator: String) -> Vec<String> {
+    if separator.is_empty() {
+        s.chars().map(String::from).collect()
+    } else {
+        s.split(&separator).map(String::from).collect()
+    }
+}
+
+fn chr(code: u32) -> Result<String, String> {
+    char::from_u32(code)
+        .map(String::from)
+        .ok_or_else(|| format!("{code} is not a Unicode code point."))
+}
+
+fn ord(c: String) -> Result<u32, String> {
+    let mut chars = c.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c as u32),
+        _ => Err(format!("Expected a single character but got '{c}'.")),
+    }
+}
+
+// Seconds since the Unix epoch, as in
+// [10.2.1 Telling time](https://craftinginterpreters.com/functions.html#telling-time).
+fn clock() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::Capabilities;
+    use crate::engine::{Config, Lox, LoxError};
+    use crate::value::Value;
+
+    fn lox(capabilities: Capabilities) -> Lox {
+        Lox::with_config(Config {
+            capabilities,
+            ..Config::default()
+        })
+    }
+
+    // These helpers are shared with the tests of the submodules.
+
+    /// Evaluates `source` with a new engine that has no capabilities.
+    pub(super) fn eval(source: &str) -> Result<Value, String> {
+        eval_with(&mut Lox::new(), source)
+    }
+
+    /// Evaluates `source` with `lox`, failing with the message of a runtime error.
+    pub(super) fn eval_with(lox: &mut Lox, source: &str) -> Result<Value, String> {
+        lox.eval(source).map_err(|error| match error {
+            LoxError::Runtime(error) => error.message,
+            error => panic!("{error}"),
+        })
+    }
+
+    pub(super) fn string(s: &str) -> Result<Value, String> {
+        Ok(Value::String(String::from(s)))
+    }
+
+    pub(super) fn number(n: f64) -> Result<Value, String> {
+        Ok(Value::Number(n))
+    }
+
+    #[test]
+    fn it_installs_only_the_natives_capabilities_allow() {
+        assert_eq!(lox(Capabilities::none()).get("clock"), None);
+
+        let mut lox = lox(Capabilities {
+            clock: true,
+            ..Capabilities::none()
+        });
+        assert!(matches!(lox.eval("clock()").unwrap(), Value::Number(n) if n > 0.0));
+    }
+
+    #[test]
+    fn it_converts_to_and_from_strings() {
+        assert_eq!(
+            eval("str(1.5) + str(nil) + str(true)"),
+            string("1.5niltrue")
+        );
+        assert_eq!(eval("num(\" -12.5 \") + num(\"3\")"), number(-9.5));
+        assert_eq!(eval("num(4)"), number(4.0));
+        for bad in ["\"\"", "\"1.\"", "\".5\"", "\"1e3\"", "\"inf\"", "\"--1\""] {
+            assert!(eval(&format!("num({bad})")).is_err(), "{bad}");
+        }
+        assert_eq!(
+            eval("num(\"abc\")"),
+            Err(String::from("Could not convert 'abc' to a number."))
+        );
+        assert_eq!(
+            eval("num(nil)"),
+            Err(String::from("Expected a string but got nil."))
+        );
+    }
+
+    #[test]
+    fn it_names_types() {
+        assert_eq!(eval("type(1)"), string("number"));
+        assert_eq!(eval("type(\"a\")"), string("string"));
+        assert_eq!(eval("type(nil)"), string("nil"));
+        assert_eq!(eval("type(false)"), string("boolean"));
+        assert_eq!(eval("fun f() {} type(f)"), string("function"));
+        assert_eq!(eval("type(type)"), string("function"));
+        assert_eq!(eval("type(split(\"a\", \",\"))"), string("list"));
+    }
+
+    #[test]
+    fn it_measures_and_slices_strings() {
+        assert_eq!(eval("len(\"héllo\")"), number(5.0));
+        assert_eq!(eval("len(split(\"a,b,c\", \",\"))"), number(3.0));
+        assert_eq!(
+            eval("len(1)"),
+            Err(String::from(
+                "Expected a string, list or map but got number."
+            ))
+        );
+
+        assert_eq!(eval("substr(\"héllo\", 1, 4)"), string("éll"));
+        assert_eq!(eval("substr(\"abc\", 3, 3)"), string(""));
+        assert_eq!(
+            eval("substr(\"abc\", 1, 4)"),
+            Err(String::from(
+                "Substring 1 to 4 is out of range for a string of length 3."
+            ))
+        );
+        assert!(eval("substr(\"abc\", 2, 1)").is_err());
+        assert!(eval("substr(\"abc\", 0.5, 1)").is_err());
+
+        assert_eq!(eval("index_of(\"héllo\", \"l\")"), number(2.0));
+        assert_eq!(eval("index_of(\"abc\", \"\")"), number(0.0));
+        assert_eq!(eval("index_of(\"abc\", \"d\")"), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn it_changes_case_and_trims() {
+        assert_eq!(eval("upper(\"Straße\")"), string("STRASSE"));
+        assert_eq!(eval("lower(\"ÀB\")"), string("àb"));
+        assert_eq!(eval("trim(\" \t a b \n\")"), string("a b"));
+        assert!(eval("upper(1)").is_err());
+    }
+
+    #[test]
+    fn it_splits_strings() {
+        let list = |items: &[&str]| {
+            Ok(Value::List(Rc::new(
+                items
+                    .iter()
+                    .map(|s| Value::String(String::from(*s)))
+                    .collect(),
+            )))
+        };
+
+        assert_eq!(eval("split(\"a, b,\", \", \")"), list(&["a", "b,"]));
+        assert_eq!(eval("split(\"a,,b\", \",\")"), list(&["a", "", "b"]));
+        assert_eq!(eval("split(\"hé\", \"\")"), list(&["h", "é"]));
+        assert_eq!(eval("split(\"\", \",\")"), list(&[""]));
+    }
+
+    #[test]
+    fn it_converts_characters_to_code_points() {
+        assert_eq!(eval("chr(65) + chr(233)"), string("Aé"));
+        assert_eq!(eval("ord(\"A\") + ord(\"é\")"), number(298.0));
+        assert_eq!(
+            eval("chr(55296)"),
+            Err(String::from("55296 is not a Unicode code point."))
+        );
+        assert!(eval("chr(-1)").is_err());
+        assert_eq!(
+            eval("ord(\"ab\")"),
+            Err(String::from("Expected a single character but got 'ab'."))
+        );
+        assert!(eval("ord(\"\")").is_err());
+    }
+}
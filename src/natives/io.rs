@@ -0,0 +1,210 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use super::Capabilities;
+use crate::callable::{Arity, NativeError};
+use crate::convert::FromLox;
+use crate::engine::Lox;
+
+/**
+Defines the natives for files and the process around the script that `capabilities` allow as
+globals of `lox`:
+
+- `read_file(path)` and `file_exists(path)`, with `fs_read`
+- `write_file(path, text)` and `append_file(path, text)`, with `fs_write`; both create the file if
+  it doesn't exist
+- `env(name)`, the environment variable `name` or `nil`, `args()`, the list of `args`, and
+  `exit(code)`, with `env`
+- `read_line()`, the next line of standard input without its line ending, or `nil` at the end of
+  the input, with `stdout`
+
+`exit` stops the script, which then fails with `LoxError::Exit` carrying the code; the host
+decides whether to end its process with it, as the CLI does.
+*/
+pub(super) fn install(lox: &mut Lox, capabilities: &Capabilities, args: &[String]) {
+    if capabilities.fs_read {
+        lox.define_function("read_file", |path: String| {
+            fs::read_to_string(&path).map_err(|e| failed("read", &path, e))
+        });
+        lox.define_function("file_exists", |path: String| Path::new(&path).exists());
+    }
+
+    if capabilities.fs_write {
+        lox.define_function("write_file", |path: String, text: String| {
+            fs::write(&path, text).map_err(|e| failed("write", &path, e))
+        });
+        lox.define_function("append_file", |path: String, text: String| {
+            OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(text.as_bytes()))
+                .map_err(|e| failed("append to", &path, e))
+        });
+    }
+
+    if capabilities.env {
+        let args = args.to_vec();
+        lox.define_function("env", |name: String| env::var(name).ok());
+        lox.define_function("args", move || args.clone());
+        lox.define_native("exit", Arity::Fixed(1), |arguments| {
+            Err(NativeError::Exit(i32::from_lox(&arguments[0])?))
+        });
+    }
+
+    if capabilities.stdout {
+        lox.define_function("read_line", read_line);
+    }
+}
+
+fn failed(action: &str, path: &str, error: io::Error) -> String {
+    format!("Could not {action} '{path}': {error}.")
+}
+
+fn read_line() -> Result<Option<String>, String> {
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) => Ok(None),
+        Ok(_) => {
+            let end = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(end);
+            Ok(Some(line))
+        }
+        Err(e) => Err(format!("Could not read standard input: {e}.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::rc::Rc;
+
+    use crate::engine::{Config, Lox, LoxError};
+    use crate::natives::tests::{eval_with, string};
+    use crate::natives::Capabilities;
+    use crate::output::Buffer;
+    use crate::value::Value;
+
+    #[test]
+    fn it_reads_and_writes_files() {
+        let path = env::temp_dir().join("rlox_it_reads_and_writes_files.txt");
+        let _ = fs::remove_file(&path);
+        let mut lox = Lox::with_config(Config {
+            capabilities: Capabilities::all(),
+            ..Config::default()
+        });
+        lox.define("path", Value::String(path.to_string_lossy().into_owned()));
+
+        assert_eq!(
+            eval_with(&mut lox, "file_exists(path)"),
+            Ok(Value::Boolean(false))
+        );
+        let error = eval_with(&mut lox, "read_file(path)").unwrap_err();
+        assert!(error.starts_with("Could not read '"), "{error}");
+
+        eval_with(&mut lox, "append_file(path, \"a\")").unwrap();
+        eval_with(&mut lox, "append_file(path, \"b\")").unwrap();
+        assert_eq!(eval_with(&mut lox, "read_file(path)"), string("ab"));
+        eval_with(&mut lox, "write_file(path, \"c\")").unwrap();
+        assert_eq!(eval_with(&mut lox, "read_file(path)"), string("c"));
+        assert_eq!(
+            eval_with(&mut lox, "file_exists(path)"),
+            Ok(Value::Boolean(true))
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_exposes_the_process() {
+        let mut lox = Lox::with_config(Config {
+            capabilities: Capabilities::all(),
+            args: vec![String::from("a"), String::from("b c")],
+            ..Config::default()
+        });
+
+        assert_eq!(
+            eval_with(&mut lox, "args()"),
+            Ok(Value::List(Rc::new(vec![
+                Value::String(String::from("a")),
+                Value::String(String::from("b c")),
+            ])))
+        );
+        let path = env::var("PATH").unwrap();
+        assert_eq!(eval_with(&mut lox, "env(\"PATH\")"), string(&path));
+        assert_eq!(
+            eval_with(&mut lox, "env(\"RLOX_IT_EXPOSES_THE_PROCESS\")"),
+            Ok(Value::Nil)
+        );
+    }
+
+    #[test]
+    fn it_hands_the_exit_status_back_to_the_host() {
+        let output = Buffer::new();
+        let mut lox = Lox::with_config(Config {
+            capabilities: Capabilities::all(),
+            ..Config::default()
+        });
+        lox.set_output(output.clone());
+
+        let error = lox
+            .run("fun f() { exit(3); } print 1; f(); print 2;")
+            .unwrap_err();
+
+        assert!(matches!(error, LoxError::Exit(3)), "{error:?}");
+        assert_eq!(error.exit_code(), 3);
+        assert_eq!(output.contents(), "1\n");
+        // The engine is still usable afterwards.
+        assert_eq!(eval_with(&mut lox, "1 + 1"), Ok(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn it_exits_from_the_middle_of_an_expression() {
+        let output = Buffer::new();
+        let mut lox = Lox::with_config(Config {
+            capabilities: Capabilities::all(),
+            ..Config::default()
+        });
+        lox.set_output(output.clone());
+
+        let error = lox
+            .run("fun s() { print \"side effect\"; return 1; } print exit(3) + s();")
+            .unwrap_err();
+
+        assert!(matches!(error, LoxError::Exit(3)), "{error:?}");
+        assert_eq!(output.contents(), "");
+    }
+
+    #[test]
+    fn it_installs_each_native_with_its_capability() {
+        let only = |grant: fn(&mut Capabilities)| {
+            let mut capabilities = Capabilities::none();
+            grant(&mut capabilities);
+            capabilities
+        };
+        let natives = [
+            ("read_file", only(|c| c.fs_read = true)),
+            ("file_exists", only(|c| c.fs_read = true)),
+            ("write_file", only(|c| c.fs_write = true)),
+            ("append_file", only(|c| c.fs_write = true)),
+            ("env", only(|c| c.env = true)),
+            ("args", only(|c| c.env = true)),
+            ("exit", only(|c| c.env = true)),
+            ("read_line", only(|c| c.stdout = true)),
+        ];
+
+        for (name, capabilities) in natives {
+            let lox = |capabilities| {
+                Lox::with_config(Config {
+                    capabilities,
+                    ..Config::default()
+                })
+            };
+            assert!(lox(capabilities).get(name).is_some(), "{name}");
+            assert!(lox(Capabilities::none()).get(name).is_none(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::cell::Cell;
+use std::f64::consts;
+use std::rc::Rc;
+
+use crate::callable::{Arity, NativeResult};
+use crate::engine::Lox;
+use crate::value::Value;
+
+/**
+Defines the math natives and constants as globals of `lox`:
+
+- `PI` and `E`
+- `sqrt`, `pow`, `abs`, `floor`, `ceil`, `round`, `exp` and `log` (the natural logarithm)
+- `sin`, `cos`, `tan` and `atan2(y, x)`, in radians
+- `min` and `max` of one or more numbers
+- `random()`, between 0 inclusive and 1 exclusive, and `random_int(low, high)`, both inclusive
+
+`PI` and `E` are ordinary global variables, like every native, so a script can assign to them, e.g.
+`PI = 3;`, and later code run by the same engine sees the new value.
+
+Arguments outside of a function's domain, such as `sqrt(-1)`, raise a runtime error rather than
+producing `NaN`. The random numbers come from a generator started at `seed`, so a script run with
+the same seed always gets the same ones.
+*/
+pub(super) fn install(lox: &mut Lox, seed: u64) {
+    lox.define("PI", Value::Number(consts::PI));
+    lox.define("E", Value::Number(consts::E));
+
+    lox.define_function("sqrt", |x: f64| {
+        domain(x >= 0.0, "Can't take the square root of a negative number.")?;
+        Ok(x.sqrt())
+    });
+    lox.define_function("pow", |base: f64, exponent: f64| {
+        domain(
+            base >= 0.0 || exponent.fract() == 0.0,
+            "Can't raise a negative number to a fractional power.",
+        )?;
+        Ok(base.powf(exponent))
+    });
+    lox.define_function("abs", f64::abs);
+    lox.define_function("floor", f64::floor);
+    lox.define_function("ceil", f64::ceil);
+    lox.define_function("round", f64::round);
+    lox.define_function("exp", f64::exp);
+    lox.define_function("log", |x: f64| {
+        domain(
+            x > 0.0,
+            "Can't take the logarithm of a number that isn't positive.",
+        )?;
+        Ok(x.ln())
+    });
+    lox.define_function("sin", f64::sin);
+    lox.define_function("cos", f64::cos);
+    lox.define_function("tan", f64::tan);
+    lox.define_function("atan2", f64::atan2);
+    lox.define_native("min", Arity::Variadic, |arguments| {
+        extreme(arguments, f64::min)
+    });
+    lox.define_native("max", Arity::Variadic, |arguments| {
+        extreme(arguments, f64::max)
+    });
+
+    let state = Rc::new(Cell::new(seed));
+    let random = Rc::clone(&state);
+    lox.define_function("random", move || {
+        // The top 53 bits make up every `f64` in [0, 1) that is a multiple of 2^-53.
+        (next_random(&random) >> 11) as f64 / (1u64 << 53) as f64
+    });
+    lox.define_function("random_int", move |low: i64, high: i64| {
+        if low > high {
+            return Err(format!("Expected low <= high but got {low} > {high}."));
+        }
+
+        // Scaling rather than taking the remainder keeps every number in the range equally likely,
+        // short of a bias too small to matter.
+        let range = (high as i128 - low as i128 + 1) as u128;
+        let offset = (next_random(&state) as u128 * range) >> 64;
+        Ok((low as i128 + offset as i128) as i64)
+    });
+}
+
+fn domain(holds: bool, message: &str) -> Result<(), String> {
+    if holds {
+        Ok(())
+    } else {
+        Err(String::from(message))
+    }
+}
+
+fn extreme(arguments: &[Value], pick: fn(f64, f64) -> f64) -> NativeResult {
+    let Some((first, rest)) = arguments.split_first() else {
+        return Err(String::from("Expected at least one number.").into());
+    };
+
+    let mut extreme = first.as_number()?;
+    for argument in rest {
+        extreme = pick(extreme, argument.as_number()?);
+    }
+
+    Ok(Value::Number(extreme))
+}
+
+// SplitMix64, which is small, fast and good enough for scripts, though not for cryptography.
+fn next_random(state: &Cell<u64>) -> u64 {
+    let next = state.get().wrapping_add(0x9E37_79B9_7F4A_7C15);
+    state.set(next);
+
+    let mut z = next;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts;
+
+    use crate::engine::{Config, Lox};
+    use crate::natives::tests::{eval, eval_with, number};
+    use crate::value::Value;
+
+    #[test]
+    fn it_defines_constants() {
+        assert_eq!(eval("PI"), number(consts::PI));
+        assert_eq!(eval("E"), number(consts::E));
+        // They're globals like any other, so scripts can reassign them.
+        assert_eq!(eval("PI = 3; PI"), number(3.0));
+    }
+
+    #[test]
+    fn it_computes_functions() {
+        assert_eq!(eval("sqrt(16)"), number(4.0));
+        assert_eq!(eval("pow(2, 10)"), number(1024.0));
+        assert_eq!(eval("pow(-2, 3)"), number(-8.0));
+        assert_eq!(eval("abs(-1.5)"), number(1.5));
+        assert_eq!(eval("floor(-1.5)"), number(-2.0));
+        assert_eq!(eval("ceil(-1.5)"), number(-1.0));
+        assert_eq!(eval("round(2.5)"), number(3.0));
+        assert_eq!(eval("round(-2.5)"), number(-3.0));
+        assert_eq!(eval("exp(0)"), number(1.0));
+        assert_eq!(eval("log(E)"), number(1.0));
+        assert_eq!(eval("sin(0) + cos(0) + tan(0)"), number(1.0));
+        assert_eq!(eval("atan2(1, 1) * 4"), number(consts::PI));
+        assert_eq!(eval("min(3, -1, 2)"), number(-1.0));
+        assert_eq!(eval("max(3)"), number(3.0));
+    }
+
+    #[test]
+    fn it_reports_domain_errors() {
+        assert_eq!(
+            eval("sqrt(-1)"),
+            Err(String::from(
+                "Can't take the square root of a negative number."
+            ))
+        );
+        assert_eq!(
+            eval("pow(-8, 1 / 3)"),
+            Err(String::from(
+                "Can't raise a negative number to a fractional power."
+            ))
+        );
+        assert!(eval("log(0)").is_err());
+        assert!(eval("log(-1)").is_err());
+        assert_eq!(
+            eval("min()"),
+            Err(String::from("Expected at least one number."))
+        );
+        assert!(eval("max(1, \"2\")").is_err());
+        assert!(eval("floor(\"1\")").is_err());
+    }
+
+    #[test]
+    fn it_generates_the_same_random_numbers_for_a_seed() {
+        let numbers = |seed| {
+            let mut lox = Lox::with_config(Config {
+                seed,
+                ..Config::default()
+            });
+            (0..10)
+                .map(|_| eval_with(&mut lox, "random()").unwrap())
+                .collect::<Vec<Value>>()
+        };
+
+        assert_eq!(numbers(1), numbers(1));
+        assert_ne!(numbers(1), numbers(2));
+        for number in numbers(3) {
+            assert!(matches!(number, Value::Number(n) if (0.0..1.0).contains(&n)));
+        }
+    }
+
+    #[test]
+    fn it_generates_random_integers_in_range() {
+        let mut lox = Lox::new();
+
+        let mut seen = [false; 3];
+        for _ in 0..100 {
+            let Ok(Value::Number(n)) = eval_with(&mut lox, "random_int(-1, 1)") else {
+                panic!("random_int should return a number");
+            };
+            assert_eq!(n.fract(), 0.0);
+            seen[(n + 1.0) as usize] = true;
+        }
+        assert_eq!(seen, [true; 3]);
+
+        assert_eq!(eval("random_int(5, 5)"), number(5.0));
+        assert_eq!(
+            eval("random_int(2, 1)"),
+            Err(String::from("Expected low <= high but got 2 > 1."))
+        );
+        assert!(eval("random_int(0, 1.5)").is_err());
+    }
+}
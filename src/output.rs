@@ -0,0 +1,72 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/**
+An in-memory sink that can be handed to a `Lox` engine while the host keeps a handle to read back
+what was written to it, e.g. to capture what a script prints:
+
+```
+use rlox::{output::Buffer, Lox};
+
+let output = Buffer::new();
+let mut lox = Lox::new();
+lox.set_output(output.clone());
+lox.run("print 1 + 2;").unwrap();
+assert_eq!(output.contents(), "3\n");
+```
+
+Clones share the same bytes.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Buffer {
+    bytes: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Buffer {
+    pub fn new() -> Buffer {
+        Buffer::default()
+    }
+
+    /// Everything written so far, with any invalid UTF-8 replaced.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.bytes.borrow()).into_owned()
+    }
+
+    /// Empties the buffer, returning what it held.
+    pub fn take(&self) -> String {
+        let bytes = self.bytes.take();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::Buffer;
+
+    #[test]
+    fn it_shares_its_contents_between_clones() {
+        let buffer = Buffer::new();
+        let mut writer = buffer.clone();
+
+        write!(writer, "a").unwrap();
+        writeln!(writer, "b").unwrap();
+        assert_eq!(buffer.contents(), "ab\n");
+
+        assert_eq!(buffer.take(), "ab\n");
+        assert_eq!(writer.contents(), "");
+    }
+}
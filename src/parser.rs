@@ -1,40 +1,479 @@
-use crate::expr::{Expr, LiteralType};
-use crate::token::{Token, TokenType};
+use std::mem;
+use std::rc::Rc;
 
-pub struct Parser {
+use crate::expr::{Ast, Expr, ExprId, LiteralType};
+use crate::stmt::{FunctionDecl, Stmt};
+use crate::token::{Span, Token, TokenType};
+
+/**
+Mirrors the `ParseError` exception in [6.3.2 Entering panic mode](https://craftinginterpreters.com/parsing-expressions.html#entering-panic-mode).
+Rather than being reported as soon as they're found, errors are handed back to the caller so it can
+decide what to do with them; the REPL, for instance, waits for more input instead of reporting an
+error at the end of an incomplete line.
+*/
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub token: Token,
+    pub message: String,
+    /// Whether the parser had run out of tokens, meaning more input could make the source valid.
+    /// This isn't always the same as `token` being the end of the input since some errors point
+    /// back at an earlier token, such as the `?` of a conditional that is missing its `:`.
+    pub unexpected_eof: bool,
+}
+
+pub struct Parser<'a> {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<ParseError>,
+    ast: &'a mut Ast,
+    repl: bool,
+    /// How many function bodies the parser is inside of, which says whether `return` is allowed.
+    function_depth: usize,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Parser {
+impl<'a> Parser<'a> {
+    /// Creates a parser that allocates the nodes it parses into `ast`.
+    pub fn new(tokens: Vec<Token>, ast: &'a mut Ast) -> Parser<'a> {
         Parser {
             tokens: tokens.clone(),
             current: 0,
+            errors: vec![],
+            ast,
+            repl: false,
+            function_depth: 0,
+        }
+    }
+
+    /// Parses the tokens as a program, returning every error found if there were any.
+    // program        → declaration* EOF ;
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let mut statements = vec![];
+        while !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        // Error productions record their error and keep parsing, so even a successfully parsed
+        // tree must not be used if any errors were recorded along the way.
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(mem::take(&mut self.errors))
+        }
+    }
+
+    /// Parses a chunk of REPL input, where the last expression statement may leave off its `;`
+    /// so that typing `1 + 2` is enough to see its value.
+    pub fn parse_repl(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        self.repl = true;
+        self.parse()
+    }
+
+    /// Parses the tokens as a single expression.
+    pub fn parse_expression(&mut self) -> Result<ExprId, Vec<ParseError>> {
+        match self.expression() {
+            Ok(expr) if self.errors.is_empty() => Ok(expr),
+            _ => Err(mem::take(&mut self.errors)),
+        }
+    }
+    // declaration    → funDecl | varDecl | statement ;
+    fn declaration(&mut self) -> Option<Stmt> {
+        let statement = if self.match_type(vec![TokenType::Fun]) {
+            self.function()
+        } else if self.match_type(vec![TokenType::Var]) {
+            self.var_declaration()
+        } else {
+            self.statement()
+        };
+
+        match statement {
+            Ok(statement) => Some(statement),
+            Err(_) => {
+                self.synchronize();
+                None
+            }
+        }
+    }
+    // funDecl        → "fun" function ;
+    // function       → IDENTIFIER "(" parameters? ")" block ;
+    // parameters     → IDENTIFIER ( "," IDENTIFIER )* ;
+    fn function(&mut self) -> Result<Stmt, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, String::from("Expect function name."))?
+            .clone();
+        self.consume(
+            TokenType::LeftParen,
+            String::from("Expect '(' after function name."),
+        )?;
+
+        let mut params = vec![];
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= 255 {
+                    let token = self.peek().unwrap().clone();
+                    self.error(&token, String::from("Can't have more than 255 parameters."));
+                }
+                params.push(
+                    self.consume(
+                        TokenType::Identifier,
+                        String::from("Expect parameter name."),
+                    )?
+                    .clone(),
+                );
+
+                if !self.match_type(vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(
+            TokenType::RightParen,
+            String::from("Expect ')' after parameters."),
+        )?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            String::from("Expect '{' before function body."),
+        )?;
+        self.function_depth += 1;
+        let body = self.block();
+        self.function_depth -= 1;
+
+        Ok(Stmt::Function(Rc::new(FunctionDecl {
+            name,
+            params,
+            body: body?,
+        })))
+    }
+    // varDecl        → "var" IDENTIFIER ( "=" expression )? ";" ;
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, String::from("Expect variable name."))?
+            .clone();
+
+        let mut initializer = None;
+        if self.match_type(vec![TokenType::Equal]) {
+            initializer = Some(self.expression()?);
+        }
+
+        self.consume(
+            TokenType::Semicolon,
+            String::from("Expect ';' after variable declaration."),
+        )?;
+        Ok(Stmt::Var { name, initializer })
+    }
+    // statement      → exprStmt | forStmt | ifStmt | printStmt | returnStmt | whileStmt
+    //                  | block ;
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_type(vec![TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.match_type(vec![TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.match_type(vec![TokenType::Print]) {
+            return self.print_statement();
+        }
+        if self.match_type(vec![TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.match_type(vec![TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.match_type(vec![TokenType::LeftBrace]) {
+            return Ok(Stmt::Block(self.block()?));
+        }
+
+        self.expression_statement()
+    }
+    // forStmt        → "for" "(" ( varDecl | exprStmt | ";" )
+    //                  expression? ";"
+    //                  expression? ")" statement ;
+    // The loop is desugared into a `while` loop; see
+    // [9.5.1 Desugaring](https://craftinginterpreters.com/control-flow.html#desugaring).
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().unwrap().clone();
+        self.consume(
+            TokenType::LeftParen,
+            String::from("Expect '(' after 'for'."),
+        )?;
+
+        let initializer = if self.match_type(vec![TokenType::Semicolon]) {
+            None
+        } else if self.match_type(vec![TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(
+            TokenType::Semicolon,
+            String::from("Expect ';' after loop condition."),
+        )?;
+
+        let increment = if self.check(TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(
+            TokenType::RightParen,
+            String::from("Expect ')' after for clauses."),
+        )?;
+
+        let mut body = self.statement()?;
+
+        if let Some(increment) = increment {
+            body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
+        }
+
+        // A missing condition loops forever. The `true` standing in for it has no span since it
+        // isn't in the source.
+        let condition = match condition {
+            Some(condition) => condition,
+            None => self.ast.add(Expr::Literal(LiteralType::True(true))),
+        };
+        body = Stmt::While {
+            keyword,
+            condition,
+            body: Box::new(body),
+        };
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+    // ifStmt         → "if" "(" expression ")" statement ( "else" statement )? ;
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftParen, String::from("Expect '(' after 'if'."))?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            String::from("Expect ')' after if condition."),
+        )?;
+
+        let then_branch = Box::new(self.statement()?);
+        // The `else` binds to the nearest `if`, which is the one whose branch was just parsed.
+        let else_branch = if self.match_type(vec![TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+    // printStmt      → "print" expression ";" ;
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let value = self.expression()?;
+        self.consume(
+            TokenType::Semicolon,
+            String::from("Expect ';' after value."),
+        )?;
+        Ok(Stmt::Print(value))
+    }
+    // returnStmt     → "return" expression? ";" ;
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().unwrap().clone();
+        if self.function_depth == 0 {
+            // As with an invalid assignment target, the parser knows exactly where it is.
+            self.error(&keyword, String::from("Can't return from top-level code."));
+        }
+
+        let value = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(
+            TokenType::Semicolon,
+            String::from("Expect ';' after return value."),
+        )?;
+
+        Ok(Stmt::Return { keyword, value })
+    }
+    // whileStmt      → "while" "(" expression ")" statement ;
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().unwrap().clone();
+        self.consume(
+            TokenType::LeftParen,
+            String::from("Expect '(' after 'while'."),
+        )?;
+        let condition = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            String::from("Expect ')' after condition."),
+        )?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While {
+            keyword,
+            condition,
+            body,
+        })
+    }
+    // exprStmt       → expression ";" ;
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expression()?;
+        if !(self.repl && self.is_at_end()) {
+            self.consume(
+                TokenType::Semicolon,
+                String::from("Expect ';' after expression."),
+            )?;
+        }
+        Ok(Stmt::Expression(expr))
+    }
+    // block          → "{" declaration* "}" ;
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = vec![];
+
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        self.consume(
+            TokenType::RightBrace,
+            String::from("Expect '}' after block."),
+        )?;
+        Ok(statements)
+    }
+    // expression     → comma ;
+    fn expression(&mut self) -> Result<ExprId, ParseError> {
+        self.comma()
+    }
+    // comma          → assignment ( "," assignment )* ;
+    fn comma(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
+        let mut expr = self.assignment()?;
+        while self.match_type(vec![TokenType::Comma]) {
+            let right = self.assignment()?;
+            expr = self.add(Expr::Comma { left: expr, right }, start);
+        }
+
+        Ok(expr)
+    }
+    // assignment     → IDENTIFIER "=" assignment | ternary ;
+    fn assignment(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
+        let expr = self.ternary()?;
+
+        if self.match_type(vec![TokenType::Equal]) {
+            let equals = self.previous().unwrap().clone();
+            let value = self.assignment()?;
+
+            if let Expr::Variable(name) = &self.ast[expr] {
+                let name = name.clone();
+                return Ok(self.add(Expr::Assign { name, value }, start));
+            }
+
+            // The parser isn't confused about where it is, so the error is recorded without
+            // unwinding.
+            self.error(&equals, String::from("Invalid assignment target."));
         }
+
+        Ok(expr)
+    }
+    // ternary        → logic_or ( "?" expression ":" ternary )? ;
+    fn ternary(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
+        let condition = self.or()?;
+        if self.match_type(vec![TokenType::Question]) {
+            let question = self.previous().unwrap().clone();
+            let then_branch = self.expression()?;
+            if !self.match_type(vec![TokenType::Colon]) {
+                return Err(self.error(
+                    &question,
+                    String::from("Expect ':' after then branch of conditional expression."),
+                ));
+            }
+            // Recursing into `ternary` rather than looping makes the operator right-associative.
+            let else_branch = self.ternary()?;
+            return Ok(self.add(
+                Expr::Ternary {
+                    condition,
+                    then_branch,
+                    else_branch,
+                },
+                start,
+            ));
+        }
+
+        Ok(condition)
     }
-    // expression     → equality ;
-    fn expression(&mut self) -> Box<Expr> {
-        self.equality()
+    // logic_or       → logic_and ( "or" logic_and )* ;
+    fn or(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
+        let mut expr = self.and()?;
+        while self.match_type(vec![TokenType::Or]) {
+            let operator = self.previous().unwrap().clone();
+            let right = self.and()?;
+            expr = self.add(
+                Expr::Logical {
+                    left: expr,
+                    operator,
+                    right,
+                },
+                start,
+            );
+        }
+
+        Ok(expr)
+    }
+    // logic_and      → equality ( "and" equality )* ;
+    fn and(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
+        let mut expr = self.equality()?;
+        while self.match_type(vec![TokenType::And]) {
+            let operator = self.previous().unwrap().clone();
+            let right = self.equality()?;
+            expr = self.add(
+                Expr::Logical {
+                    left: expr,
+                    operator,
+                    right,
+                },
+                start,
+            );
+        }
+
+        Ok(expr)
     }
     // equality       → comparison ( ( "!=" | "==" ) comparison )* ;
-    fn equality(&mut self) -> Box<Expr> {
-        let mut expr = self.comparison();
+    fn equality(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
+        let mut expr = self.comparison()?;
         while self.match_type(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
             let operator = self.previous().unwrap().clone();
-            let right = self.comparison();
-            expr = Box::new(Expr::Binary {
-                left: expr,
-                operator,
-                right,
-            })
+            let right = self.comparison()?;
+            expr = self.add(
+                Expr::Binary {
+                    left: expr,
+                    operator,
+                    right,
+                },
+                start,
+            )
         }
 
-        expr
+        Ok(expr)
     }
     // comparison     → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
-    fn comparison(&mut self) -> Box<Expr> {
-        let mut expr = self.term();
+    fn comparison(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
+        let mut expr = self.term()?;
         while self.match_type(vec![
             TokenType::Greater,
             TokenType::GreaterEqual,
@@ -42,89 +481,220 @@ impl Parser {
             TokenType::LessEqual,
         ]) {
             let operator = self.previous().unwrap().clone();
-            let right = self.term();
-            expr = Box::new(Expr::Binary {
-                left: expr,
-                operator,
-                right,
-            });
+            let right = self.term()?;
+            expr = self.add(
+                Expr::Binary {
+                    left: expr,
+                    operator,
+                    right,
+                },
+                start,
+            );
         }
 
-        expr
+        Ok(expr)
     }
     // term           → factor ( ( "-" | "+" ) factor )* ;
-    fn term(&mut self) -> Box<Expr> {
-        let mut expr = self.factor();
+    fn term(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
+        let mut expr = self.factor()?;
         while self.match_type(vec![TokenType::Minus, TokenType::Plus]) {
             let operator = self.previous().unwrap().clone();
-            let right = self.factor();
-            expr = Box::new(Expr::Binary {
-                left: expr,
-                operator,
-                right,
-            });
+            let right = self.factor()?;
+            expr = self.add(
+                Expr::Binary {
+                    left: expr,
+                    operator,
+                    right,
+                },
+                start,
+            );
         }
 
-        expr
+        Ok(expr)
     }
     // factor         → unary ( ( "/" | "*" ) unary )* ;
-    fn factor(&mut self) -> Box<Expr> {
-        let mut expr = self.unary();
+    fn factor(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
+        let mut expr = self.unary()?;
         while self.match_type(vec![TokenType::Slash, TokenType::Star]) {
             let operator = self.previous().unwrap().clone();
-            let right = self.unary();
-            expr = Box::new(Expr::Binary {
-                left: expr,
-                operator,
-                right,
-            });
+            let right = self.unary()?;
+            expr = self.add(
+                Expr::Binary {
+                    left: expr,
+                    operator,
+                    right,
+                },
+                start,
+            );
         }
 
-        expr
+        Ok(expr)
     }
-    // unary          → ( "!" | "-" ) unary | primary ;
-    fn unary(&mut self) -> Box<Expr> {
+    // unary          → ( "!" | "-" ) unary | call ;
+    fn unary(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
         if self.match_type(vec![TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous().unwrap().clone();
-            let right = self.unary();
-            return Box::new(Expr::Unary { operator, right });
+            let right = self.unary()?;
+            return Ok(self.add(Expr::Unary { operator, right }, start));
         }
 
-        self.primary()
+        self.call()
     }
-    // primary        → NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")" ;
-    fn primary(&mut self) -> Box<Expr> {
+    // call           → primary ( "(" arguments? ")" )* ;
+    fn call(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
+        let mut expr = self.primary()?;
+
+        while self.match_type(vec![TokenType::LeftParen]) {
+            expr = self.finish_call(expr, start)?;
+        }
+
+        Ok(expr)
+    }
+    // arguments      → assignment ( "," assignment )* ;
+    // Arguments are parsed with `assignment` rather than `expression` so that the commas between
+    // them aren't taken for comma operators.
+    fn finish_call(&mut self, callee: ExprId, start: usize) -> Result<ExprId, ParseError> {
+        let mut arguments = vec![];
+
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= 255 {
+                    let token = self.peek().unwrap().clone();
+                    self.error(&token, String::from("Can't have more than 255 arguments."));
+                }
+                arguments.push(self.assignment()?);
+
+                if !self.match_type(vec![TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self
+            .consume(
+                TokenType::RightParen,
+                String::from("Expect ')' after arguments."),
+            )?
+            .clone();
+        Ok(self.add(
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            },
+            start,
+        ))
+    }
+    // primary        → NUMBER | STRING | "true" | "false" | "nil" | "(" expression ")"
+    //                  | IDENTIFIER
+    //                  // Error productions for binary operators without a left operand:
+    //                  | "," assignment
+    //                  | ( "!=" | "==" ) comparison
+    //                  | ( ">" | ">=" | "<" | "<=" ) term
+    //                  | "+" factor
+    //                  | ( "/" | "*" ) unary ;
+    fn primary(&mut self) -> Result<ExprId, ParseError> {
+        let start = self.next_start();
         if self.match_type(vec![TokenType::False]) {
-            return Box::new(Expr::Literal(LiteralType::False(false)));
+            return Ok(self.add(Expr::Literal(LiteralType::False(false)), start));
         }
         if self.match_type(vec![TokenType::True]) {
-            return Box::new(Expr::Literal(LiteralType::True(true)));
+            return Ok(self.add(Expr::Literal(LiteralType::True(true)), start));
         }
         if self.match_type(vec![TokenType::Nil]) {
-            return Box::new(Expr::Literal(LiteralType::Nil(())));
+            return Ok(self.add(Expr::Literal(LiteralType::Nil(())), start));
         }
         if self.match_type(vec![TokenType::Number, TokenType::String]) {
             if let Some(previous) = self.previous() {
                 if previous.token_type == TokenType::Number {
-                    return Box::new(Expr::Literal(LiteralType::Number(
-                        previous.literal.parse().unwrap(),
-                    )));
+                    let literal = LiteralType::Number(previous.literal.parse().unwrap());
+                    return Ok(self.add(Expr::Literal(literal), start));
                 } else {
-                    return Box::new(Expr::Literal(LiteralType::String(previous.literal.clone())));
+                    let literal = LiteralType::String(previous.literal.clone());
+                    return Ok(self.add(Expr::Literal(literal), start));
                 }
             }
         }
 
+        if self.match_type(vec![TokenType::Identifier]) {
+            let name = self.previous().unwrap().clone();
+            return Ok(self.add(Expr::Variable(name), start));
+        }
+
         if self.match_type(vec![TokenType::LeftParen]) {
-            let expr = self.expression();
+            let expr = self.expression()?;
             self.consume(
                 TokenType::RightParen,
                 String::from("Expect ')' after expression."),
-            );
-            return Box::new(Expr::Grouping(expr));
+            )?;
+            return Ok(self.add(Expr::Grouping(expr), start));
         }
 
-        Box::new(Expr::Literal(LiteralType::Nil(())))
+        if self.match_type(vec![TokenType::Comma]) {
+            return self.missing_left_operand(Parser::assignment);
+        }
+        if self.match_type(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
+            return self.missing_left_operand(Parser::comparison);
+        }
+        if self.match_type(vec![
+            TokenType::Greater,
+            TokenType::GreaterEqual,
+            TokenType::Less,
+            TokenType::LessEqual,
+        ]) {
+            return self.missing_left_operand(Parser::term);
+        }
+        // `-` is left out since it is also a valid unary operator.
+        if self.match_type(vec![TokenType::Plus]) {
+            return self.missing_left_operand(Parser::factor);
+        }
+        if self.match_type(vec![TokenType::Slash, TokenType::Star]) {
+            return self.missing_left_operand(Parser::unary);
+        }
+
+        let token = self.peek().unwrap().clone();
+        Err(self.error(&token, String::from("Expect expression.")))
+    }
+
+    /**
+    Reports a binary operator found at the start of an expression, then parses and discards its
+    right operand with `operand`, the rule for the operator's right-hand side, so that parsing can
+    carry on as if the operator had been used correctly.
+    */
+    fn missing_left_operand(
+        &mut self,
+        operand: fn(&mut Parser<'a>) -> Result<ExprId, ParseError>,
+    ) -> Result<ExprId, ParseError> {
+        let operator = self.previous().unwrap().clone();
+        let start = operator.span.start;
+        self.error(
+            &operator,
+            format!(
+                "Binary operator '{}' requires a left operand.",
+                operator.text
+            ),
+        );
+
+        operand(self)?;
+
+        Ok(self.add(Expr::Literal(LiteralType::Nil(())), start))
+    }
+
+    /// Adds `expr` to the tree, recording that it spans from `start` to the end of the last token
+    /// consumed.
+    fn add(&mut self, expr: Expr, start: usize) -> ExprId {
+        let end = self.previous().unwrap().span.end;
+        let id = self.ast.add(expr);
+        self.ast.set_span(id, Span::new(start, end));
+        id
+    }
+
+    fn next_start(&self) -> usize {
+        self.peek().unwrap().span.start
     }
 
     fn match_type(&mut self, token_types: Vec<TokenType>) -> bool {
@@ -176,5 +746,139 @@ impl Parser {
         self.tokens.get(self.current - 1)
     }
 
-    fn consume(&mut self, token_type: TokenType, message: String) {}
+    fn consume(&mut self, token_type: TokenType, message: String) -> Result<&Token, ParseError> {
+        if self.check(token_type) {
+            return Ok(self.advance().unwrap());
+        }
+
+        let token = self.peek().unwrap().clone();
+        Err(self.error(&token, message))
+    }
+
+    fn error(&mut self, token: &Token, message: String) -> ParseError {
+        let error = ParseError {
+            token: token.clone(),
+            message,
+            unexpected_eof: self.is_at_end(),
+        };
+        self.errors.push(error.clone());
+        error
+    }
+
+    // Discards tokens until it finds a statement boundary; see
+    // [6.3.3 Synchronizing a recursive descent parser](https://craftinginterpreters.com/parsing-expressions.html#synchronizing-a-recursive-descent-parser).
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().unwrap().token_type == TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().unwrap().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => {}
+            }
+
+            self.advance();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+    use crate::expr::Ast;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn it_rejects_a_ternary_without_a_colon() {
+        let mut scanner = Scanner::new("true ? 1 2 + 3");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+
+        let errors = Parser::new(tokens, &mut ast)
+            .parse_expression()
+            .unwrap_err();
+        assert_eq!(errors[0].token.text, "?");
+        assert!(!errors[0].unexpected_eof);
+    }
+
+    #[test]
+    fn it_flags_errors_at_the_end_of_input() {
+        let mut scanner = Scanner::new("(1 + 2");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+
+        let errors = Parser::new(tokens, &mut ast)
+            .parse_expression()
+            .unwrap_err();
+        assert!(errors[0].unexpected_eof);
+    }
+
+    #[test]
+    fn it_recovers_from_a_binary_operator_without_a_left_operand() {
+        let mut scanner = Scanner::new("== 3 + 4");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let mut parser = Parser::new(tokens, &mut ast);
+
+        let errors = parser.parse_expression().unwrap_err();
+        // The right operand is consumed rather than reported as a second error.
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "Binary operator '==' requires a left operand."
+        );
+        assert!(parser.is_at_end());
+    }
+
+    #[test]
+    fn it_reports_every_bad_statement() {
+        let mut scanner = Scanner::new("var 1; print 2; 1 = 2; var a = 3;");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+
+        let errors = Parser::new(tokens, &mut ast).parse().unwrap_err();
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            ["Expect variable name.", "Invalid assignment target."]
+        );
+    }
+
+    #[test]
+    fn it_lets_the_last_repl_expression_leave_off_its_semicolon() {
+        let mut scanner = Scanner::new("var a = 1; a + 1");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+
+        assert_eq!(
+            Parser::new(tokens.clone(), &mut ast)
+                .parse_repl()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(Parser::new(tokens, &mut ast).parse().is_err());
+    }
+
+    #[test]
+    fn it_only_allows_return_inside_functions() {
+        let mut scanner = Scanner::new("fun f() { { return 1; } } return;");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+
+        let errors = Parser::new(tokens, &mut ast).parse().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Can't return from top-level code.");
+        assert_eq!(errors[0].token.span.start, 26);
+    }
 }
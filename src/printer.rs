@@ -0,0 +1,51 @@
+use crate::ast_printer::AstPrinter;
+use crate::dot_printer::DotPrinter;
+use crate::expr::{Ast, ExprId};
+use crate::json::JsonPrinter;
+use crate::rpn_printer::RpnPrinter;
+use crate::source_printer::SourcePrinter;
+use crate::stmt::Stmt;
+
+/// Renders an expression tree, or a whole program, as text in some notation.
+pub trait Printer {
+    fn print(&mut self, ast: &Ast, expr: ExprId) -> String;
+    fn print_program(&mut self, ast: &Ast, statements: &[Stmt]) -> String;
+}
+
+/// The notations the CLI can print an expression in, selected with `--format`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notation {
+    /// Lisp-style S-expressions: `(* (- 123) (group 45.67))`.
+    Sexpr,
+    /// Reverse Polish Notation: `123 ~ 45.67 *`.
+    Rpn,
+    /// Lox source that can be parsed again: `-123 * (45.67)`.
+    Source,
+    /// A Graphviz `digraph` with one node per expression.
+    Dot,
+    /// A versioned JSON document; see `json`.
+    Json,
+}
+
+impl Notation {
+    pub fn from_name(name: &str) -> Option<Notation> {
+        match name {
+            "sexpr" => Some(Notation::Sexpr),
+            "rpn" => Some(Notation::Rpn),
+            "source" => Some(Notation::Source),
+            "dot" => Some(Notation::Dot),
+            "json" => Some(Notation::Json),
+            _ => None,
+        }
+    }
+
+    pub fn printer(&self) -> Box<dyn Printer> {
+        match self {
+            Notation::Sexpr => Box::new(AstPrinter {}),
+            Notation::Rpn => Box::new(RpnPrinter {}),
+            Notation::Source => Box::new(SourcePrinter {}),
+            Notation::Dot => Box::new(DotPrinter::new()),
+            Notation::Json => Box::new(JsonPrinter {}),
+        }
+    }
+}
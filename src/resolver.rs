@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{
+    expr::{Ast, ExprId, LiteralType},
+    stmt::{FunctionDecl, Stmt},
+    token::Token,
+    visitor::{
+        walk_assign, walk_call, walk_grouping, walk_operands, walk_ternary, StmtVisitor, Visitor,
+    },
+};
+
+/// A misuse of a variable the resolver found before the program ran, pointing at the variable.
+#[derive(Debug, Clone)]
+pub struct ResolveError {
+    pub token: Token,
+    pub message: String,
+}
+
+/**
+Works out which scope each local variable use refers to before the program runs, following
+[11 Resolving and Binding](https://craftinginterpreters.com/resolving-and-binding.html).
+
+Resolving a program gives the number of scopes between each use of a local variable and the scope
+that declares it, keyed by the `ExprId` of the use, for `Interpreter::resolve`. Uses that aren't
+found in any enclosing scope are left out and looked up in the globals when they run, which is
+what lets later REPL input use the globals of earlier input. Resolving is also where a variable
+read in its own initializer or declared twice in the same local scope is reported.
+*/
+pub struct Resolver {
+    /// The local scopes enclosing the node being resolved, innermost last. Each maps a variable's
+    /// name to whether its initializer has been resolved yet.
+    scopes: Vec<HashMap<String, bool>>,
+    locals: HashMap<ExprId, usize>,
+    errors: Vec<ResolveError>,
+}
+
+impl Resolver {
+    pub fn new() -> Resolver {
+        Resolver {
+            scopes: vec![],
+            locals: HashMap::new(),
+            errors: vec![],
+        }
+    }
+
+    /// Resolves `statements`, returning the distance to each local variable use, or every error
+    /// found.
+    pub fn resolve(
+        mut self,
+        ast: &Ast,
+        statements: &[Stmt],
+    ) -> Result<HashMap<ExprId, usize>, Vec<ResolveError>> {
+        self.resolve_stmts(ast, statements);
+
+        if self.errors.is_empty() {
+            Ok(self.locals)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn resolve_stmts(&mut self, ast: &Ast, statements: &[Stmt]) {
+        for statement in statements {
+            self.visit_stmt(ast, statement);
+        }
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.insert(name.text.clone(), false).is_some() {
+            self.errors.push(ResolveError {
+                token: name.clone(),
+                message: String::from("Already a variable with this name in this scope."),
+            });
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.text.clone(), true);
+        }
+    }
+
+    fn resolve_local(&mut self, id: ExprId, name: &Token) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.text));
+        if let Some(distance) = found {
+            self.locals.insert(id, distance);
+        }
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Resolver {
+        Resolver::new()
+    }
+}
+
+impl StmtVisitor<()> for Resolver {
+    fn visit_block_stmt(&mut self, ast: &Ast, statements: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        self.resolve_stmts(ast, statements);
+        self.scopes.pop();
+    }
+
+    fn visit_expression_stmt(&mut self, ast: &Ast, expr: ExprId) {
+        self.visit_expr(ast, expr);
+    }
+
+    fn visit_function_stmt(&mut self, ast: &Ast, declaration: &Rc<FunctionDecl>) {
+        // The name is defined before the body is resolved so that the function can call itself.
+        self.declare(&declaration.name);
+        self.define(&declaration.name);
+
+        // The parameters and body share one scope, as they do when the function is called.
+        self.scopes.push(HashMap::new());
+        for param in &declaration.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_stmts(ast, &declaration.body);
+        self.scopes.pop();
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) {
+        self.visit_expr(ast, condition);
+        self.visit_stmt(ast, then_branch);
+        if let Some(else_branch) = else_branch {
+            self.visit_stmt(ast, else_branch);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, ast: &Ast, expr: ExprId) {
+        self.visit_expr(ast, expr);
+    }
+
+    fn visit_return_stmt(&mut self, ast: &Ast, _keyword: &Token, value: Option<ExprId>) {
+        if let Some(value) = value {
+            self.visit_expr(ast, value);
+        }
+    }
+
+    fn visit_var_stmt(&mut self, ast: &Ast, name: &Token, initializer: Option<ExprId>) {
+        self.declare(name);
+        if let Some(initializer) = initializer {
+            self.visit_expr(ast, initializer);
+        }
+        self.define(name);
+    }
+
+    fn visit_while_stmt(&mut self, ast: &Ast, _keyword: &Token, condition: ExprId, body: &Stmt) {
+        self.visit_expr(ast, condition);
+        self.visit_stmt(ast, body);
+    }
+}
+
+impl Visitor<()> for Resolver {
+    fn visit_assign(&mut self, ast: &Ast, id: ExprId, name: &Token, value: ExprId) {
+        walk_assign(self, ast, value);
+        self.resolve_local(id, name);
+    }
+
+    fn visit_binary(&mut self, ast: &Ast, left: ExprId, _operator: &Token, right: ExprId) {
+        walk_operands(self, ast, left, right);
+    }
+
+    fn visit_call(&mut self, ast: &Ast, callee: ExprId, _paren: &Token, arguments: &[ExprId]) {
+        walk_call(self, ast, callee, arguments);
+    }
+
+    fn visit_comma(&mut self, ast: &Ast, left: ExprId, right: ExprId) {
+        walk_operands(self, ast, left, right);
+    }
+
+    fn visit_grouping(&mut self, ast: &Ast, inner: ExprId) {
+        walk_grouping(self, ast, inner);
+    }
+
+    fn visit_literal(&mut self, _ast: &Ast, _literal: &LiteralType) {}
+
+    fn visit_logical(&mut self, ast: &Ast, left: ExprId, _operator: &Token, right: ExprId) {
+        walk_operands(self, ast, left, right);
+    }
+
+    fn visit_ternary(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    ) {
+        walk_ternary(self, ast, condition, then_branch, else_branch);
+    }
+
+    fn visit_unary(&mut self, ast: &Ast, _operator: &Token, right: ExprId) {
+        self.visit_expr(ast, right);
+    }
+
+    fn visit_variable(&mut self, _ast: &Ast, id: ExprId, name: &Token) {
+        if self.scopes.last().and_then(|scope| scope.get(&name.text)) == Some(&false) {
+            self.errors.push(ResolveError {
+                token: name.clone(),
+                message: String::from("Can't read local variable in its own initializer."),
+            });
+        }
+
+        self.resolve_local(id, name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Resolver;
+    use crate::engine::Lox;
+    use crate::expr::Ast;
+    use crate::output::Buffer;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn resolve(source: &str) -> Result<usize, Vec<String>> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        Resolver::new()
+            .resolve(&ast, &statements)
+            .map(|locals| locals.len())
+            .map_err(|errors| errors.into_iter().map(|e| e.message).collect())
+    }
+
+    #[test]
+    fn it_only_resolves_local_variables() {
+        assert_eq!(resolve("var a = 1; print a; a = 2;"), Ok(0));
+        assert_eq!(resolve("{ var a = 1; print a; a = 2; } print a;"), Ok(2));
+        assert_eq!(resolve("fun f(a) { return a + b; }"), Ok(1));
+    }
+
+    #[test]
+    fn it_reports_misused_variables() {
+        assert_eq!(
+            resolve("{ var a = 1; { var a = a; } }"),
+            Err(vec![String::from(
+                "Can't read local variable in its own initializer."
+            )])
+        );
+        assert_eq!(
+            resolve("{ var a; var a; } fun f(b, b) {}"),
+            Err(vec![
+                String::from("Already a variable with this name in this scope."),
+                String::from("Already a variable with this name in this scope."),
+            ])
+        );
+        // Globals may be redeclared, and read in their own initializer.
+        assert_eq!(resolve("var a; var a = a;"), Ok(0));
+    }
+
+    #[test]
+    fn it_binds_closures_to_the_variables_they_were_declared_with() {
+        let output = Buffer::new();
+        let mut lox = Lox::new();
+        lox.set_output(output.clone());
+
+        lox.run(
+            "var a = \"global\";
+             {
+               fun show() { print a; }
+               show();
+               var a = \"block\";
+               show();
+             }",
+        )
+        .unwrap();
+
+        assert_eq!(output.contents(), "global\nglobal\n");
+    }
+}
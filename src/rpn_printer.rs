@@ -0,0 +1,262 @@
+use std::rc::Rc;
+
+use crate::{
+    expr::{Ast, ExprId, LiteralType},
+    printer::Printer,
+    stmt::{FunctionDecl, Stmt},
+    token::{Token, TokenType},
+    visitor::{
+        walk_assign, walk_call, walk_grouping, walk_operands, walk_ternary, walk_unary,
+        StmtVisitor, Visitor,
+    },
+};
+
+/**
+Prints expressions in Reverse Polish Notation, where every operator follows its operands, e.g.
+`(1 + 2) * (4 - 3)` prints as `1 2 + 4 3 - *`. See the challenges at the end of
+[5 Representing Code](https://craftinginterpreters.com/representing-code.html#challenges).
+
+Groupings don't need to be printed since the order of the operators already encodes precedence.
+Unary minus is printed as `~` so it can't be mistaken for subtraction.
+
+Statements are printed the same way, with the keyword or `;` after the expressions they contain, e.g.
+`var a = 1 + 2;` prints as `a 1 2 + var`.
+*/
+pub struct RpnPrinter {}
+
+impl RpnPrinter {
+    fn postfix(&self, operator: &str, operands: Vec<String>) -> String {
+        let mut str = operands.join(" ");
+
+        str.push(' ');
+        str.push_str(operator);
+
+        str
+    }
+}
+
+impl Printer for RpnPrinter {
+    fn print(&mut self, ast: &Ast, expr: ExprId) -> String {
+        self.visit_expr(ast, expr)
+    }
+
+    fn print_program(&mut self, ast: &Ast, statements: &[Stmt]) -> String {
+        statements
+            .iter()
+            .map(|statement| self.visit_stmt(ast, statement))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl StmtVisitor<String> for RpnPrinter {
+    fn visit_block_stmt(&mut self, ast: &Ast, statements: &[Stmt]) -> String {
+        let mut operands = vec![String::from("{")];
+        for statement in statements {
+            operands.push(self.visit_stmt(ast, statement));
+        }
+        self.postfix("}", operands)
+    }
+
+    fn visit_expression_stmt(&mut self, ast: &Ast, expr: ExprId) -> String {
+        let expr = self.visit_expr(ast, expr);
+        self.postfix(";", vec![expr])
+    }
+
+    fn visit_function_stmt(&mut self, ast: &Ast, declaration: &Rc<FunctionDecl>) -> String {
+        let mut operands = vec![declaration.name.text.clone()];
+        operands.extend(declaration.params.iter().map(|param| param.text.clone()));
+        operands.push(self.visit_block_stmt(ast, &declaration.body));
+        // Like calls, the operator says how many parameters there are.
+        self.postfix(&format!("fun/{}", declaration.params.len()), operands)
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> String {
+        let mut operands = vec![
+            self.visit_expr(ast, condition),
+            self.visit_stmt(ast, then_branch),
+        ];
+        // Like calls, the operator says how many operands it takes.
+        let operator = match else_branch {
+            Some(else_branch) => {
+                operands.push(self.visit_stmt(ast, else_branch));
+                "if-else"
+            }
+            None => "if",
+        };
+        self.postfix(operator, operands)
+    }
+
+    fn visit_print_stmt(&mut self, ast: &Ast, expr: ExprId) -> String {
+        let expr = self.visit_expr(ast, expr);
+        self.postfix("print", vec![expr])
+    }
+
+    fn visit_return_stmt(&mut self, ast: &Ast, _keyword: &Token, value: Option<ExprId>) -> String {
+        match value {
+            Some(value) => {
+                let value = self.visit_expr(ast, value);
+                self.postfix("return", vec![value])
+            }
+            None => String::from("return"),
+        }
+    }
+
+    fn visit_var_stmt(&mut self, ast: &Ast, name: &Token, initializer: Option<ExprId>) -> String {
+        let mut operands = vec![name.text.clone()];
+        if let Some(initializer) = initializer {
+            operands.push(self.visit_expr(ast, initializer));
+        }
+        self.postfix("var", operands)
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        ast: &Ast,
+        _keyword: &Token,
+        condition: ExprId,
+        body: &Stmt,
+    ) -> String {
+        let condition = self.visit_expr(ast, condition);
+        let body = self.visit_stmt(ast, body);
+        self.postfix("while", vec![condition, body])
+    }
+}
+
+impl Visitor<String> for RpnPrinter {
+    fn visit_assign(&mut self, ast: &Ast, _id: ExprId, name: &Token, value: ExprId) -> String {
+        let value = walk_assign(self, ast, value);
+        self.postfix("=", vec![name.text.clone(), value])
+    }
+
+    fn visit_binary(&mut self, ast: &Ast, left: ExprId, operator: &Token, right: ExprId) -> String {
+        let (left, right) = walk_operands(self, ast, left, right);
+        self.postfix(&operator.text, vec![left, right])
+    }
+
+    fn visit_call(
+        &mut self,
+        ast: &Ast,
+        callee: ExprId,
+        _paren: &Token,
+        arguments: &[ExprId],
+    ) -> String {
+        let (callee, arguments) = walk_call(self, ast, callee, arguments);
+        // The number of arguments can't be told from the output otherwise.
+        let operator = format!("call/{}", arguments.len());
+        let mut operands = vec![callee];
+        operands.extend(arguments);
+        self.postfix(&operator, operands)
+    }
+
+    fn visit_comma(&mut self, ast: &Ast, left: ExprId, right: ExprId) -> String {
+        let (left, right) = walk_operands(self, ast, left, right);
+        self.postfix(",", vec![left, right])
+    }
+
+    fn visit_grouping(&mut self, ast: &Ast, inner: ExprId) -> String {
+        walk_grouping(self, ast, inner)
+    }
+
+    fn visit_literal(&mut self, _ast: &Ast, literal: &LiteralType) -> String {
+        match literal {
+            LiteralType::Number(n) => n.to_string(),
+            LiteralType::String(s) => s.clone(),
+            LiteralType::True(t) => t.to_string(),
+            LiteralType::False(f) => f.to_string(),
+            LiteralType::Nil(()) => String::from("nil"),
+        }
+    }
+
+    fn visit_logical(
+        &mut self,
+        ast: &Ast,
+        left: ExprId,
+        operator: &Token,
+        right: ExprId,
+    ) -> String {
+        let (left, right) = walk_operands(self, ast, left, right);
+        self.postfix(&operator.text, vec![left, right])
+    }
+
+    fn visit_ternary(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    ) -> String {
+        let (condition, then_branch, else_branch) =
+            walk_ternary(self, ast, condition, then_branch, else_branch);
+        self.postfix("?:", vec![condition, then_branch, else_branch])
+    }
+
+    fn visit_unary(&mut self, ast: &Ast, operator: &Token, right: ExprId) -> String {
+        let right = walk_unary(self, ast, right);
+        let operator = match operator.token_type {
+            TokenType::Minus => "~",
+            _ => &operator.text,
+        };
+        self.postfix(operator, vec![right])
+    }
+
+    fn visit_variable(&mut self, _ast: &Ast, _id: ExprId, name: &Token) -> String {
+        name.text.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RpnPrinter;
+    use crate::expr::Ast;
+    use crate::parser::Parser;
+    use crate::printer::Printer;
+    use crate::scanner::Scanner;
+
+    fn print_source(source: &str) -> String {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let expr = Parser::new(tokens, &mut ast).parse_expression().unwrap();
+
+        RpnPrinter {}.print(&ast, expr)
+    }
+
+    #[test]
+    fn it_prints_operators_after_their_operands() {
+        assert_eq!(print_source("(1 + 2) * (4 - 3)"), "1 2 + 4 3 - *");
+        assert_eq!(print_source("1 + 2 * 3"), "1 2 3 * +");
+        assert_eq!(print_source("true ? 1 : 2, 3"), "true 1 2 ?: 3 ,");
+    }
+
+    #[test]
+    fn it_disambiguates_unary_minus() {
+        assert_eq!(print_source("-123 * (45.67)"), "123 ~ 45.67 *");
+        assert_eq!(print_source("1 - -2"), "1 2 ~ -");
+        assert_eq!(print_source("!true"), "true !");
+    }
+
+    #[test]
+    fn it_prints_the_number_of_arguments_of_calls() {
+        assert_eq!(print_source("f(1, g())"), "f 1 g call/0 call/2");
+    }
+
+    #[test]
+    fn it_prints_statements() {
+        let mut scanner = Scanner::new("var a = 1 + 2; { a = -a; print a; }");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        assert_eq!(
+            RpnPrinter {}.print_program(&ast, &statements),
+            "a 1 2 + var\n{ a a ~ = ; a print }"
+        );
+    }
+}
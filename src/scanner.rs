@@ -1,7 +1,35 @@
 use std::{collections::HashMap, iter::Peekable, str::Chars};
 
-use crate::errors;
-use crate::token::{Token, TokenType};
+use crate::token::{Span, Token, TokenType};
+
+/// An error found while scanning, handed back to the caller rather than reported straight away.
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub line: usize,
+    pub message: String,
+    /// Whether the source ended in the middle of a token, meaning more input could complete it.
+    pub unexpected_eof: bool,
+}
+
+/// The reserved words of Lox and the token each one scans to.
+pub const KEYWORDS: [(&str, TokenType); 16] = [
+    ("and", TokenType::And),
+    ("class", TokenType::Class),
+    ("else", TokenType::Else),
+    ("false", TokenType::False),
+    ("for", TokenType::For),
+    ("fun", TokenType::Fun),
+    ("if", TokenType::If),
+    ("nil", TokenType::Nil),
+    ("or", TokenType::Or),
+    ("print", TokenType::Print),
+    ("return", TokenType::Return),
+    ("super", TokenType::Super),
+    ("this", TokenType::This),
+    ("true", TokenType::True),
+    ("var", TokenType::Var),
+    ("while", TokenType::While),
+];
 
 pub struct Scanner<'a> {
     source: &'a str,
@@ -10,6 +38,7 @@ pub struct Scanner<'a> {
     start: usize,
     current: usize,
     line: usize,
+    errors: Vec<ScanError>,
     keywords: HashMap<String, TokenType>,
 }
 
@@ -25,24 +54,11 @@ impl Scanner<'_> {
             start: 0,
             current: 0,
             line: 1,
-            keywords: HashMap::from([
-                (String::from("and"), TokenType::And),
-                (String::from("class"), TokenType::Class),
-                (String::from("else"), TokenType::Else),
-                (String::from("false"), TokenType::False),
-                (String::from("for"), TokenType::For),
-                (String::from("fun"), TokenType::Fun),
-                (String::from("if"), TokenType::If),
-                (String::from("nil"), TokenType::Nil),
-                (String::from("or"), TokenType::Or),
-                (String::from("print"), TokenType::Print),
-                (String::from("return"), TokenType::Return),
-                (String::from("super"), TokenType::Super),
-                (String::from("this"), TokenType::This),
-                (String::from("true"), TokenType::This),
-                (String::from("var"), TokenType::Var),
-                (String::from("while"), TokenType::While),
-            ]),
+            errors: vec![],
+            keywords: KEYWORDS
+                .iter()
+                .map(|(name, token_type)| (String::from(*name), token_type.clone()))
+                .collect(),
         }
     }
 
@@ -57,6 +73,7 @@ impl Scanner<'_> {
             String::new(),
             String::new(),
             self.line,
+            Span::new(self.current, self.current),
         ));
 
         &self.tokens
@@ -75,6 +92,8 @@ impl Scanner<'_> {
             '+' => self.add_token(TokenType::Plus, String::new()),
             ';' => self.add_token(TokenType::Semicolon, String::new()),
             '*' => self.add_token(TokenType::Star, String::new()),
+            '?' => self.add_token(TokenType::Question, String::new()),
+            ':' => self.add_token(TokenType::Colon, String::new()),
             '!' => {
                 if self.is_match('=') {
                     self.add_token(TokenType::BangEqual, String::new());
@@ -124,7 +143,7 @@ impl Scanner<'_> {
                 } else if self.is_alpha(c) {
                     self.identifier(c);
                 } else {
-                    errors::handle(self.line, format!("Unrecognized token: {}", char));
+                    self.error(format!("Unrecognized token: {}", char), false);
                 }
             }
         }
@@ -167,34 +186,24 @@ impl Scanner<'_> {
     fn number(&mut self, c: char) {
         let mut number_chars = vec![c];
 
-        let next = self.peek();
-
-        while self.is_digit(next) {
+        loop {
+            let next = self.peek();
+            if !self.is_digit(next) {
+                break;
+            }
             number_chars.push(self.advance().unwrap());
         }
 
-        /*
-        [4.6.2 Number literals](https://craftinginterpreters.com/scanning.html#number-literals)
-        introduces a `peekNext` method to look ahead
-        by 2 indices; however, this lookahead is complicated using the peekable
-        iterator. For now, I'll try to handle this case differently; however,
-        if `peekNext` is used in the future in a way that I can't account for with
-        "manual" logic, I may have to look for
-        [an alternative](https://stackoverflow.com/questions/62186871/how-to-correctly-use-peek-in-rust).
-         */
-        if next == '.' {
-            let fraction_char = self.advance().unwrap();
-            let next = self.peek();
-            if self.is_digit(next) {
-                number_chars.push(fraction_char);
-                loop {
-                    let a = self.advance();
-                    number_chars.push(a.unwrap());
-                    let next = self.peek();
-                    if !self.is_digit(next) {
-                        break;
-                    }
+        // Only consume the '.' when a digit follows it so that `1.` scans as a
+        // number followed by a dot rather than silently swallowing the dot.
+        if self.peek() == '.' && self.is_digit(self.peek_next()) {
+            number_chars.push(self.advance().unwrap());
+            loop {
+                let next = self.peek();
+                if !self.is_digit(next) {
+                    break;
                 }
+                number_chars.push(self.advance().unwrap());
             }
         }
 
@@ -213,7 +222,7 @@ impl Scanner<'_> {
         }
 
         if self.is_at_end() {
-            errors::handle(self.line, String::from("Unterminated string."));
+            self.error(String::from("Unterminated string."), true);
             return;
         }
 
@@ -223,6 +232,22 @@ impl Scanner<'_> {
         self.add_token(TokenType::String, value);
     }
 
+    pub fn errors(&self) -> &[ScanError] {
+        &self.errors
+    }
+
+    pub fn had_error(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    fn error(&mut self, message: String, unexpected_eof: bool) {
+        self.errors.push(ScanError {
+            line: self.line,
+            message,
+            unexpected_eof,
+        });
+    }
+
     fn is_match(&mut self, expected: char) -> bool {
         if self.is_at_end() {
             return false;
@@ -244,12 +269,21 @@ impl Scanner<'_> {
         *self.source_iter.peek().unwrap()
     }
 
+    /**
+    [4.6.2 Number literals](https://craftinginterpreters.com/scanning.html#number-literals)
+    introduces a `peekNext` method to look ahead by 2 characters. The peekable iterator only
+    offers a single character of lookahead, so this reads from the remaining source instead.
+    */
+    fn peek_next(&self) -> char {
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
+    }
+
     fn is_digit(&self, c: char) -> bool {
-        c >= '0' && c <= '9'
+        c.is_ascii_digit()
     }
 
     fn is_alpha(&self, c: char) -> bool {
-        return (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_';
+        c.is_ascii_alphabetic() || c == '_'
     }
 
     fn is_alphanumeric(&self, c: char) -> bool {
@@ -262,12 +296,49 @@ impl Scanner<'_> {
 
     fn advance(&mut self) -> Option<char> {
         let char = self.source_iter.next();
-        self.current += 1;
+        // `current` is a byte offset so that `start..current` always slices the lexeme, even
+        // when the source contains multi-byte characters.
+        self.current += char.map_or(1, char::len_utf8);
         char
     }
 
-    fn add_token(&mut self, token_type: TokenType, text: String) {
-        self.tokens
-            .push(Token::new(token_type, text, String::new(), self.line))
+    fn add_token(&mut self, token_type: TokenType, literal: String) {
+        let text = String::from(&self.source[self.start..self.current]);
+        self.tokens.push(Token::new(
+            token_type,
+            text,
+            literal,
+            self.line,
+            Span::new(self.start, self.current),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scanner;
+    use crate::token::TokenType;
+
+    #[test]
+    fn it_scans_conditional_operators_and_lexemes() {
+        let mut scanner = Scanner::new("true ? 123.5 : 1.");
+        let tokens = scanner.scan_tokens();
+
+        let types: Vec<TokenType> = tokens.iter().map(|t| t.token_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::True,
+                TokenType::Question,
+                TokenType::Number,
+                TokenType::Colon,
+                TokenType::Number,
+                TokenType::Dot,
+                TokenType::Eof,
+            ]
+        );
+        assert_eq!(tokens[2].text, "123.5");
+        assert_eq!(tokens[2].literal, "123.5");
+        assert_eq!(tokens[4].literal, "1");
     }
 }
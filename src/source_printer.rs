@@ -0,0 +1,613 @@
+use std::rc::Rc;
+
+use crate::{
+    expr::{Ast, Expr, ExprId, LiteralType},
+    printer::Printer,
+    stmt::{FunctionDecl, Stmt},
+    token::Token,
+    token::TokenType,
+    visitor::{StmtVisitor, Visitor},
+};
+
+/**
+Prints expressions back out as Lox source that `Parser` accepts.
+
+`Grouping` nodes are always printed, so a tree that came from the parser prints with exactly the
+parentheses it was written with. Anything else is only parenthesized when operator precedence
+demands it, which keeps trees built or rewritten by a pass (e.g. `ConstantFolder`) readable.
+
+Programs are printed one statement per line, with the contents of blocks indented by four spaces.
+*/
+pub struct SourcePrinter {}
+
+impl SourcePrinter {
+    /// Prints `expr`, parenthesizing it if it binds looser than `min_precedence`.
+    fn operand(&mut self, ast: &Ast, expr: ExprId, min_precedence: u8) -> String {
+        let source = self.visit_expr(ast, expr);
+
+        if precedence(&ast[expr]) < min_precedence {
+            format!("({source})")
+        } else {
+            source
+        }
+    }
+}
+
+impl Printer for SourcePrinter {
+    fn print(&mut self, ast: &Ast, expr: ExprId) -> String {
+        self.visit_expr(ast, expr)
+    }
+
+    fn print_program(&mut self, ast: &Ast, statements: &[Stmt]) -> String {
+        statements
+            .iter()
+            .map(|statement| self.visit_stmt(ast, statement))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl StmtVisitor<String> for SourcePrinter {
+    fn visit_block_stmt(&mut self, ast: &Ast, statements: &[Stmt]) -> String {
+        let mut str = String::from("{\n");
+
+        for statement in statements {
+            // Nested blocks print over several lines, every one of which needs indenting.
+            for line in self.visit_stmt(ast, statement).lines() {
+                str.push_str("    ");
+                str.push_str(line);
+                str.push('\n');
+            }
+        }
+
+        str.push('}');
+
+        str
+    }
+
+    fn visit_expression_stmt(&mut self, ast: &Ast, expr: ExprId) -> String {
+        format!("{};", self.visit_expr(ast, expr))
+    }
+
+    fn visit_function_stmt(&mut self, ast: &Ast, declaration: &Rc<FunctionDecl>) -> String {
+        let params: Vec<&str> = declaration.params.iter().map(|p| p.text.as_str()).collect();
+        format!(
+            "fun {}({}) {}",
+            declaration.name.text,
+            params.join(", "),
+            self.visit_block_stmt(ast, &declaration.body)
+        )
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> String {
+        let condition = self.visit_expr(ast, condition);
+        let Some(else_branch) = else_branch else {
+            return format!("if ({condition}) {}", self.visit_stmt(ast, then_branch));
+        };
+
+        // An `else` binds to the nearest `if`, so one that belongs to an outer `if` has to be kept
+        // out of reach of an inner one without an `else` of its own by bracing it.
+        let then_branch = match then_branch {
+            Stmt::If {
+                else_branch: None, ..
+            } => self.visit_block_stmt(ast, std::slice::from_ref(then_branch)),
+            _ => self.visit_stmt(ast, then_branch),
+        };
+        format!(
+            "if ({condition}) {then_branch} else {}",
+            self.visit_stmt(ast, else_branch)
+        )
+    }
+
+    fn visit_print_stmt(&mut self, ast: &Ast, expr: ExprId) -> String {
+        format!("print {};", self.visit_expr(ast, expr))
+    }
+
+    fn visit_return_stmt(&mut self, ast: &Ast, _keyword: &Token, value: Option<ExprId>) -> String {
+        match value {
+            Some(value) => format!("return {};", self.visit_expr(ast, value)),
+            None => String::from("return;"),
+        }
+    }
+
+    fn visit_var_stmt(&mut self, ast: &Ast, name: &Token, initializer: Option<ExprId>) -> String {
+        match initializer {
+            Some(initializer) => {
+                format!("var {} = {};", name.text, self.visit_expr(ast, initializer))
+            }
+            None => format!("var {};", name.text),
+        }
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        ast: &Ast,
+        _keyword: &Token,
+        condition: ExprId,
+        body: &Stmt,
+    ) -> String {
+        format!(
+            "while ({}) {}",
+            self.visit_expr(ast, condition),
+            self.visit_stmt(ast, body)
+        )
+    }
+}
+
+impl Visitor<String> for SourcePrinter {
+    fn visit_assign(&mut self, ast: &Ast, _id: ExprId, name: &Token, value: ExprId) -> String {
+        // Assignment is right-associative, so a nested assignment needs no parentheses.
+        let value = self.operand(ast, value, ASSIGNMENT);
+        format!("{} = {value}", name.text)
+    }
+
+    fn visit_binary(&mut self, ast: &Ast, left: ExprId, operator: &Token, right: ExprId) -> String {
+        let precedence = binary_precedence(&operator.token_type);
+        // Binary operators are left-associative, so only the right operand needs parentheses
+        // when it has the same precedence.
+        let left = self.operand(ast, left, precedence);
+        let right = self.operand(ast, right, precedence + 1);
+        format!("{left} {} {right}", operator.text)
+    }
+
+    fn visit_call(
+        &mut self,
+        ast: &Ast,
+        callee: ExprId,
+        _paren: &Token,
+        arguments: &[ExprId],
+    ) -> String {
+        let callee = self.operand(ast, callee, CALL);
+        // A comma operator in an argument would be taken for the start of the next argument.
+        let arguments: Vec<String> = arguments
+            .iter()
+            .map(|argument| self.operand(ast, *argument, ASSIGNMENT))
+            .collect();
+        format!("{callee}({})", arguments.join(", "))
+    }
+
+    fn visit_comma(&mut self, ast: &Ast, left: ExprId, right: ExprId) -> String {
+        let left = self.operand(ast, left, COMMA);
+        let right = self.operand(ast, right, COMMA + 1);
+        format!("{left}, {right}")
+    }
+
+    fn visit_grouping(&mut self, ast: &Ast, inner: ExprId) -> String {
+        format!("({})", self.visit_expr(ast, inner))
+    }
+
+    fn visit_literal(&mut self, _ast: &Ast, literal: &LiteralType) -> String {
+        match literal {
+            // Lox has no literals for these, so spell out an expression that evaluates to them.
+            LiteralType::Number(n) if n.is_nan() => String::from("(0 / 0)"),
+            LiteralType::Number(n) if n.is_infinite() && *n > 0.0 => String::from("(1 / 0)"),
+            LiteralType::Number(n) if n.is_infinite() => String::from("(-1 / 0)"),
+            LiteralType::Number(n) => n.to_string(),
+            LiteralType::String(s) => format!("\"{s}\""),
+            LiteralType::True(t) => t.to_string(),
+            LiteralType::False(f) => f.to_string(),
+            LiteralType::Nil(()) => String::from("nil"),
+        }
+    }
+
+    fn visit_logical(
+        &mut self,
+        ast: &Ast,
+        left: ExprId,
+        operator: &Token,
+        right: ExprId,
+    ) -> String {
+        let precedence = logical_precedence(&operator.token_type);
+        let left = self.operand(ast, left, precedence);
+        let right = self.operand(ast, right, precedence + 1);
+        format!("{left} {} {right}", operator.text)
+    }
+
+    fn visit_ternary(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    ) -> String {
+        // The then branch is delimited by `?` and `:`, so it never needs parentheses, and the
+        // operator is right-associative, so a nested conditional in the else branch doesn't either.
+        let condition = self.operand(ast, condition, TERNARY + 1);
+        let then_branch = self.visit_expr(ast, then_branch);
+        let else_branch = self.operand(ast, else_branch, TERNARY);
+        format!("{condition} ? {then_branch} : {else_branch}")
+    }
+
+    fn visit_unary(&mut self, ast: &Ast, operator: &Token, right: ExprId) -> String {
+        let right = self.operand(ast, right, UNARY);
+        format!("{}{right}", operator.text)
+    }
+
+    fn visit_variable(&mut self, _ast: &Ast, _id: ExprId, name: &Token) -> String {
+        name.text.clone()
+    }
+}
+
+// Precedence levels from loosest to tightest, following the grammar in `Parser`.
+const COMMA: u8 = 1;
+const ASSIGNMENT: u8 = 2;
+const TERNARY: u8 = 3;
+const OR: u8 = 4;
+const AND: u8 = 5;
+const EQUALITY: u8 = 6;
+const COMPARISON: u8 = 7;
+const TERM: u8 = 8;
+const FACTOR: u8 = 9;
+const UNARY: u8 = 10;
+const CALL: u8 = 11;
+const PRIMARY: u8 = 12;
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Assign { .. } => ASSIGNMENT,
+        Expr::Call { .. } => CALL,
+        Expr::Binary { operator, .. } => binary_precedence(&operator.token_type),
+        Expr::Comma { .. } => COMMA,
+        Expr::Grouping(_) => PRIMARY,
+        // Negative numbers print with a leading `-`, so they need the same care as unary minus.
+        Expr::Literal(LiteralType::Number(n)) if *n < 0.0 => UNARY,
+        Expr::Literal(_) => PRIMARY,
+        Expr::Logical { operator, .. } => logical_precedence(&operator.token_type),
+        Expr::Ternary { .. } => TERNARY,
+        Expr::Unary { .. } => UNARY,
+        Expr::Variable(_) => PRIMARY,
+    }
+}
+
+fn logical_precedence(token_type: &TokenType) -> u8 {
+    match token_type {
+        TokenType::Or => OR,
+        _ => AND,
+    }
+}
+
+fn binary_precedence(token_type: &TokenType) -> u8 {
+    match token_type {
+        TokenType::BangEqual | TokenType::EqualEqual => EQUALITY,
+        TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => {
+            COMPARISON
+        }
+        TokenType::Minus | TokenType::Plus => TERM,
+        _ => FACTOR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SourcePrinter;
+    use crate::expr::{Ast, Expr, ExprId, LiteralType};
+    use crate::parser::Parser;
+    use crate::printer::Printer;
+    use crate::scanner::Scanner;
+    use crate::stmt::Stmt;
+    use crate::token::{Span, Token, TokenType};
+
+    fn parse(source: &str, ast: &mut Ast) -> ExprId {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().clone();
+        Parser::new(tokens, ast)
+            .parse_expression()
+            .unwrap_or_else(|_| panic!("failed to parse {source:?}"))
+    }
+
+    fn same_tree(a: &Ast, a_id: ExprId, b: &Ast, b_id: ExprId) -> bool {
+        let same_operator = |x: &Token, y: &Token| x.token_type == y.token_type;
+
+        match (&a[a_id], &b[b_id]) {
+            (
+                Expr::Assign {
+                    name: n1,
+                    value: v1,
+                },
+                Expr::Assign {
+                    name: n2,
+                    value: v2,
+                },
+            ) => n1.text == n2.text && same_tree(a, *v1, b, *v2),
+            (
+                Expr::Binary {
+                    left: l1,
+                    operator: o1,
+                    right: r1,
+                },
+                Expr::Binary {
+                    left: l2,
+                    operator: o2,
+                    right: r2,
+                },
+            ) => same_operator(o1, o2) && same_tree(a, *l1, b, *l2) && same_tree(a, *r1, b, *r2),
+            (
+                Expr::Comma {
+                    left: l1,
+                    right: r1,
+                },
+                Expr::Comma {
+                    left: l2,
+                    right: r2,
+                },
+            ) => same_tree(a, *l1, b, *l2) && same_tree(a, *r1, b, *r2),
+            (
+                Expr::Logical {
+                    left: l1,
+                    operator: o1,
+                    right: r1,
+                },
+                Expr::Logical {
+                    left: l2,
+                    operator: o2,
+                    right: r2,
+                },
+            ) => same_operator(o1, o2) && same_tree(a, *l1, b, *l2) && same_tree(a, *r1, b, *r2),
+            (Expr::Grouping(e1), Expr::Grouping(e2)) => same_tree(a, *e1, b, *e2),
+            (Expr::Literal(l1), Expr::Literal(l2)) => match (l1, l2) {
+                (LiteralType::Number(n1), LiteralType::Number(n2)) => n1 == n2,
+                (LiteralType::String(s1), LiteralType::String(s2)) => s1 == s2,
+                (LiteralType::True(_), LiteralType::True(_)) => true,
+                (LiteralType::False(_), LiteralType::False(_)) => true,
+                (LiteralType::Nil(()), LiteralType::Nil(())) => true,
+                _ => false,
+            },
+            (
+                Expr::Ternary {
+                    condition: c1,
+                    then_branch: t1,
+                    else_branch: e1,
+                },
+                Expr::Ternary {
+                    condition: c2,
+                    then_branch: t2,
+                    else_branch: e2,
+                },
+            ) => {
+                same_tree(a, *c1, b, *c2) && same_tree(a, *t1, b, *t2) && same_tree(a, *e1, b, *e2)
+            }
+            (
+                Expr::Unary {
+                    operator: o1,
+                    right: r1,
+                },
+                Expr::Unary {
+                    operator: o2,
+                    right: r2,
+                },
+            ) => same_operator(o1, o2) && same_tree(a, *r1, b, *r2),
+            (
+                Expr::Call {
+                    callee: c1,
+                    arguments: a1,
+                    ..
+                },
+                Expr::Call {
+                    callee: c2,
+                    arguments: a2,
+                    ..
+                },
+            ) => {
+                same_tree(a, *c1, b, *c2)
+                    && a1.len() == a2.len()
+                    && a1.iter().zip(a2).all(|(x, y)| same_tree(a, *x, b, *y))
+            }
+            (Expr::Variable(n1), Expr::Variable(n2)) => n1.text == n2.text,
+            _ => false,
+        }
+    }
+
+    /// A xorshift generator so the property test is reproducible without extra dependencies.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+    }
+
+    /// Generates Lox source for a random expression, deliberately mixing operators of different
+    /// precedence with and without parentheses.
+    fn generate(rng: &mut Rng, depth: u32) -> String {
+        const BINARY: [&str; 12] = [
+            "==", "!=", "<", "<=", ">", ">=", "+", "-", "*", "/", "and", "or",
+        ];
+
+        if depth == 0 {
+            return match rng.below(7) {
+                0 => rng.below(100).to_string(),
+                1 => format!("{}.{}", rng.below(100), rng.below(100) + 1),
+                2 => format!("\"s{}\"", rng.below(10)),
+                3 => String::from("true"),
+                4 => String::from("false"),
+                5 => String::from(["x", "y"][rng.below(2) as usize]),
+                _ => String::from("nil"),
+            };
+        }
+
+        match rng.below(9) {
+            0 => format!("({})", generate(rng, depth - 1)),
+            1 => format!(
+                "{}{}",
+                ["-", "!"][rng.below(2) as usize],
+                generate(rng, depth - 1)
+            ),
+            2 => format!(
+                "{} ? {} : {}",
+                generate(rng, depth - 1),
+                generate(rng, depth - 1),
+                generate(rng, depth - 1)
+            ),
+            3 => format!("{}, {}", generate(rng, depth - 1), generate(rng, depth - 1)),
+            // Only a bare variable can be assigned to, so the assignment has to be parenthesized
+            // for it to stay valid wherever it ends up.
+            4 => format!("(x = {})", generate(rng, depth - 1)),
+            5 => {
+                let arguments: Vec<String> = (0..rng.below(3))
+                    .map(|_| generate(rng, depth - 1))
+                    .collect();
+                format!("{}({})", generate(rng, depth - 1), arguments.join(", "))
+            }
+            _ => format!(
+                "{} {} {}",
+                generate(rng, depth - 1),
+                BINARY[rng.below(BINARY.len() as u64) as usize],
+                generate(rng, depth - 1)
+            ),
+        }
+    }
+
+    #[test]
+    fn it_prints_source_with_minimal_parentheses() {
+        let mut ast = Ast::new();
+        let one = ast.add(Expr::Literal(LiteralType::Number(1.0)));
+        let two = ast.add(Expr::Literal(LiteralType::Number(2.0)));
+        let three = ast.add(Expr::Literal(LiteralType::Number(3.0)));
+        let plus = Token::new(
+            TokenType::Plus,
+            String::from("+"),
+            String::new(),
+            1,
+            Span::default(),
+        );
+        let star = Token::new(
+            TokenType::Star,
+            String::from("*"),
+            String::new(),
+            1,
+            Span::default(),
+        );
+        let sum = ast.add(Expr::Binary {
+            left: one,
+            operator: plus.clone(),
+            right: two,
+        });
+        let product = ast.add(Expr::Binary {
+            left: sum,
+            operator: star,
+            right: three,
+        });
+        let nested = ast.add(Expr::Binary {
+            left: one,
+            operator: plus,
+            right: sum,
+        });
+
+        assert_eq!(SourcePrinter {}.print(&ast, product), "(1 + 2) * 3");
+        assert_eq!(SourcePrinter {}.print(&ast, nested), "1 + (1 + 2)");
+    }
+
+    #[test]
+    fn it_keeps_explicit_groupings() {
+        let mut ast = Ast::new();
+        let expr = parse("(1) + -(\"a\" == nil) ? 1, 2 : false", &mut ast);
+
+        assert_eq!(
+            SourcePrinter {}.print(&ast, expr),
+            "(1) + -(\"a\" == nil) ? 1, 2 : false"
+        );
+    }
+
+    #[test]
+    fn it_prints_statements() {
+        let mut scanner = Scanner::new("var a=1;{var b;{print a=b=(2);}}a=a,3;");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        assert_eq!(
+            SourcePrinter {}.print_program(&ast, &statements),
+            [
+                "var a = 1;",
+                "{",
+                "    var b;",
+                "    {",
+                "        print a = b = (2);",
+                "    }",
+                "}",
+                "a = a, 3;",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn it_prints_control_flow() {
+        let mut scanner = Scanner::new(
+            "while(a and b)if(a)print 1;else{a=nil;} if(a){if(b)print b;}else print a;",
+        );
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        assert_eq!(
+            SourcePrinter {}.print_program(&ast, &statements),
+            [
+                "while (a and b) if (a) print 1; else {",
+                "    a = nil;",
+                "}",
+                "if (a) {",
+                "    if (b) print b;",
+                "} else print a;",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn it_keeps_an_else_with_its_own_if() {
+        let mut scanner = Scanner::new("if (a) if (b) print b; else print a;");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let mut statements = Parser::new(tokens, &mut ast).parse().unwrap();
+
+        // The parser gives the `else` to the inner `if`; only a pass could move it to the outer one.
+        if let Stmt::If {
+            then_branch,
+            else_branch,
+            ..
+        } = &mut statements[0]
+        {
+            if let Stmt::If {
+                else_branch: inner, ..
+            } = then_branch.as_mut()
+            {
+                *else_branch = inner.take();
+            }
+        }
+
+        assert_eq!(
+            SourcePrinter {}.print_program(&ast, &statements),
+            "if (a) {\n    if (b) print b;\n} else print a;"
+        );
+    }
+
+    #[test]
+    fn it_round_trips_generated_expressions() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..500 {
+            let source = generate(&mut rng, 4);
+            let mut ast = Ast::new();
+            let expr = parse(&source, &mut ast);
+
+            let printed = SourcePrinter {}.print(&ast, expr);
+            let mut reparsed_ast = Ast::new();
+            let reparsed = parse(&printed, &mut reparsed_ast);
+
+            assert!(
+                same_tree(&ast, expr, &reparsed_ast, reparsed),
+                "{source:?} printed as {printed:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::rc::Rc;
+
+use crate::expr::ExprId;
+use crate::token::Token;
+
+/**
+A statement, following [8.1 Statements](https://craftinginterpreters.com/statements-and-state.html#statements).
+
+Statements are few enough per program that they are kept as a plain tree; the expressions inside them
+live in the `Ast` arena like any other and are referred to by `ExprId`.
+*/
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Block(Vec<Stmt>),
+    Expression(ExprId),
+    Function(Rc<FunctionDecl>),
+    If {
+        condition: ExprId,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+    },
+    Print(ExprId),
+    Return {
+        keyword: Token,
+        value: Option<ExprId>,
+    },
+    Var {
+        name: Token,
+        initializer: Option<ExprId>,
+    },
+    /// A `while` loop; `for` loops are desugared into these by the parser.
+    While {
+        /// The `while` or `for` keyword, whose location is used to report errors from the loop.
+        keyword: Token,
+        condition: ExprId,
+        body: Box<Stmt>,
+    },
+}
+
+/// A `fun` declaration. It is shared with every function created by running it, since they all need
+/// its body.
+#[derive(Debug)]
+pub struct FunctionDecl {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+}
@@ -1,6 +1,8 @@
 #![allow(dead_code)]
 
-#[derive(Debug, Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenType {
     // Single-character tokens:
     LeftParen,
@@ -14,6 +16,8 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Question,
+    Colon,
     // One-or-two character tokens:
     Bang,
     BangEqual,
@@ -47,7 +51,20 @@ pub enum TokenType {
     Eof,
 }
 
-#[derive(Debug, Clone)]
+/// The byte offsets of a piece of source code, from `start` up to but not including `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Token {
     pub token_type: TokenType,
     pub text: String,
@@ -70,15 +87,23 @@ pub struct Token {
     */
     pub literal: String,
     pub line: usize,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, text: String, literal: String, line: usize) -> Token {
+    pub fn new(
+        token_type: TokenType,
+        text: String,
+        literal: String,
+        line: usize,
+        span: Span,
+    ) -> Token {
         Token {
             token_type,
             text,
             literal,
             line,
+            span,
         }
     }
 }
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::callable::{LoxFunction, NativeFunction};
+use crate::convert::FromLox;
+
+/**
+A value produced at runtime. This plays the part of `Object` in
+[7.1 Representing Values](https://craftinginterpreters.com/evaluating-expressions.html#representing-values),
+with an enum variant standing in for each of the Java classes `jlox` uses.
+
+Lists and maps have no literals in Lox; they only come from natives, e.g. `split` or a host function
+returning a `Vec` or a `HashMap`, and are passed around unchanged.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Native(Rc<NativeFunction>),
+    Function(Rc<LoxFunction>),
+    List(Rc<Vec<Value>>),
+    Map(Rc<BTreeMap<String, Value>>),
+}
+
+impl Value {
+    // `false` and `nil` are falsey and everything else is truthy; see
+    // [7.2.4 Truthiness and falsiness](https://craftinginterpreters.com/evaluating-expressions.html#truthiness-and-falsiness).
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Boolean(false))
+    }
+
+    /// The name of the value's type, for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Boolean(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Native(_) | Value::Function(_) => "function",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+        }
+    }
+
+    /// The number inside the value, or a message saying what was found instead.
+    pub fn as_number(&self) -> Result<f64, String> {
+        Ok(f64::from_lox(self)?)
+    }
+
+    /// The string inside the value, or a message saying what was found instead.
+    pub fn as_str(&self) -> Result<&str, String> {
+        Ok(<&str>::from_lox(self)?)
+    }
+
+    /// The boolean inside the value, or a message saying what was found instead.
+    pub fn as_bool(&self) -> Result<bool, String> {
+        Ok(bool::from_lox(self)?)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Boolean(b) => write!(f, "{b}"),
+            // Unlike Java's, Rust's formatting already leaves off the `.0` of integral numbers.
+            Value::Number(n) => write!(f, "{n}"),
+            Value::String(s) => write!(f, "{s}"),
+            Value::Native(_) => write!(f, "<native fn>"),
+            Value::Function(function) => write!(f, "<fn {}>", function.name()),
+            Value::List(items) => {
+                let items: Vec<String> = items.iter().map(Value::quoted).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Value::Map(entries) => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("{key:?}: {}", value.quoted()))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+        }
+    }
+}
+
+impl Value {
+    // Strings inside a list or map are quoted so that e.g. `["a, b"]` can't be mistaken for two
+    // strings.
+    fn quoted(&self) -> String {
+        match self {
+            Value::String(s) => format!("{s:?}"),
+            other => other.to_string(),
+        }
+    }
+}
@@ -1,5 +1,250 @@
-use crate::expr::Expr;
+use std::rc::Rc;
 
+use crate::expr::{Ast, Expr, ExprId, LiteralType};
+use crate::stmt::{FunctionDecl, Stmt};
+use crate::token::Token;
+
+/**
+Walks expressions by shared reference, producing a `T` for each node.
+
+`visit_expr` dispatches to one method per `Expr` variant, mirroring the `visitBinaryExpr` family of
+methods in [5.3.3 Visitors for expressions](https://craftinginterpreters.com/representing-code.html#visitors-for-expressions).
+Children are passed as `ExprId`s into `ast`; the `walk_*` helpers below visit them and hand back
+their results for implementations that only need to combine them. Variables and assignments are also
+given their own `ExprId`, which side tables such as the resolver's are keyed by.
+
+Visiting borrows both the tree and the visitor mutably, so the same tree can be walked any number of
+times and a visitor is free to keep state (e.g. an environment) between nodes.
+*/
 pub trait Visitor<T> {
-    fn visit(&self, expr: Box<Expr>) -> T;
+    fn visit_expr(&mut self, ast: &Ast, id: ExprId) -> T {
+        match &ast[id] {
+            Expr::Assign { name, value } => self.visit_assign(ast, id, name, *value),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => self.visit_binary(ast, *left, operator, *right),
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => self.visit_call(ast, *callee, paren, arguments),
+            Expr::Comma { left, right } => self.visit_comma(ast, *left, *right),
+            Expr::Grouping(inner) => self.visit_grouping(ast, *inner),
+            Expr::Literal(literal) => self.visit_literal(ast, literal),
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => self.visit_logical(ast, *left, operator, *right),
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.visit_ternary(ast, *condition, *then_branch, *else_branch),
+            Expr::Unary { operator, right } => self.visit_unary(ast, operator, *right),
+            Expr::Variable(name) => self.visit_variable(ast, id, name),
+        }
+    }
+
+    fn visit_assign(&mut self, ast: &Ast, id: ExprId, name: &Token, value: ExprId) -> T;
+    fn visit_binary(&mut self, ast: &Ast, left: ExprId, operator: &Token, right: ExprId) -> T;
+    fn visit_call(&mut self, ast: &Ast, callee: ExprId, paren: &Token, arguments: &[ExprId]) -> T;
+    fn visit_comma(&mut self, ast: &Ast, left: ExprId, right: ExprId) -> T;
+    fn visit_grouping(&mut self, ast: &Ast, inner: ExprId) -> T;
+    fn visit_literal(&mut self, ast: &Ast, literal: &LiteralType) -> T;
+    fn visit_logical(&mut self, ast: &Ast, left: ExprId, operator: &Token, right: ExprId) -> T;
+    fn visit_ternary(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    ) -> T;
+    fn visit_unary(&mut self, ast: &Ast, operator: &Token, right: ExprId) -> T;
+    fn visit_variable(&mut self, ast: &Ast, id: ExprId, name: &Token) -> T;
+}
+
+pub fn walk_assign<T, V: Visitor<T> + ?Sized>(visitor: &mut V, ast: &Ast, value: ExprId) -> T {
+    visitor.visit_expr(ast, value)
+}
+
+/// Visits the left and right operands of a binary, comma or logical expression, in that order.
+pub fn walk_operands<T, V: Visitor<T> + ?Sized>(
+    visitor: &mut V,
+    ast: &Ast,
+    left: ExprId,
+    right: ExprId,
+) -> (T, T) {
+    let left = visitor.visit_expr(ast, left);
+    let right = visitor.visit_expr(ast, right);
+    (left, right)
+}
+
+pub fn walk_call<T, V: Visitor<T> + ?Sized>(
+    visitor: &mut V,
+    ast: &Ast,
+    callee: ExprId,
+    arguments: &[ExprId],
+) -> (T, Vec<T>) {
+    let callee = visitor.visit_expr(ast, callee);
+    let arguments = arguments
+        .iter()
+        .map(|argument| visitor.visit_expr(ast, *argument))
+        .collect();
+    (callee, arguments)
+}
+
+pub fn walk_grouping<T, V: Visitor<T> + ?Sized>(visitor: &mut V, ast: &Ast, inner: ExprId) -> T {
+    visitor.visit_expr(ast, inner)
+}
+
+pub fn walk_ternary<T, V: Visitor<T> + ?Sized>(
+    visitor: &mut V,
+    ast: &Ast,
+    condition: ExprId,
+    then_branch: ExprId,
+    else_branch: ExprId,
+) -> (T, T, T) {
+    let condition = visitor.visit_expr(ast, condition);
+    let then_branch = visitor.visit_expr(ast, then_branch);
+    let else_branch = visitor.visit_expr(ast, else_branch);
+    (condition, then_branch, else_branch)
+}
+
+pub fn walk_unary<T, V: Visitor<T> + ?Sized>(visitor: &mut V, ast: &Ast, right: ExprId) -> T {
+    visitor.visit_expr(ast, right)
+}
+
+/**
+Walks expressions by mutable reference so a pass can rewrite nodes in place.
+
+The default `visit_expr_mut` simply walks every child, so implementations only need to override it
+for the nodes they care about, calling `walk_expr_mut` to carry on into the children.
+*/
+pub trait MutVisitor {
+    fn visit_expr_mut(&mut self, ast: &mut Ast, expr: &mut Expr) {
+        walk_expr_mut(self, ast, expr);
+    }
+}
+
+/**
+Visits the node behind `id`. A node is never its own descendant, so it can be moved out of `ast`
+while it is visited; this is what lets the visitor hold `&mut Expr` and `&mut Ast` at the same time.
+*/
+pub fn visit_id_mut<V: MutVisitor + ?Sized>(visitor: &mut V, ast: &mut Ast, id: ExprId) {
+    let mut expr = ast.replace(id, Expr::Literal(LiteralType::Nil(())));
+    visitor.visit_expr_mut(ast, &mut expr);
+    ast.replace(id, expr);
+}
+
+pub fn walk_expr_mut<V: MutVisitor + ?Sized>(visitor: &mut V, ast: &mut Ast, expr: &mut Expr) {
+    match expr {
+        Expr::Assign { value, .. } => visit_id_mut(visitor, ast, *value),
+        Expr::Binary { left, right, .. }
+        | Expr::Comma { left, right }
+        | Expr::Logical { left, right, .. } => {
+            visit_id_mut(visitor, ast, *left);
+            visit_id_mut(visitor, ast, *right);
+        }
+        Expr::Call {
+            callee, arguments, ..
+        } => {
+            visit_id_mut(visitor, ast, *callee);
+            for argument in arguments.iter() {
+                visit_id_mut(visitor, ast, *argument);
+            }
+        }
+        Expr::Grouping(inner) => visit_id_mut(visitor, ast, *inner),
+        Expr::Literal(_) => {}
+        Expr::Ternary {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            visit_id_mut(visitor, ast, *condition);
+            visit_id_mut(visitor, ast, *then_branch);
+            visit_id_mut(visitor, ast, *else_branch);
+        }
+        Expr::Unary { right, .. } => visit_id_mut(visitor, ast, *right),
+        Expr::Variable(_) => {}
+    }
+}
+
+/**
+Walks statements, producing a `T` for each one, like the `Stmt.Visitor` interface in
+[8.1.1 Statement syntax trees](https://craftinginterpreters.com/statements-and-state.html#statement-syntax-trees).
+Implementations usually implement `Visitor` as well and hand the expressions in each statement to it.
+*/
+pub trait StmtVisitor<T> {
+    fn visit_stmt(&mut self, ast: &Ast, stmt: &Stmt) -> T {
+        match stmt {
+            Stmt::Block(statements) => self.visit_block_stmt(ast, statements),
+            Stmt::Expression(expr) => self.visit_expression_stmt(ast, *expr),
+            Stmt::Function(declaration) => self.visit_function_stmt(ast, declaration),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => self.visit_if_stmt(ast, *condition, then_branch, else_branch.as_deref()),
+            Stmt::Print(expr) => self.visit_print_stmt(ast, *expr),
+            Stmt::Return { keyword, value } => self.visit_return_stmt(ast, keyword, *value),
+            Stmt::Var { name, initializer } => self.visit_var_stmt(ast, name, *initializer),
+            Stmt::While {
+                keyword,
+                condition,
+                body,
+            } => self.visit_while_stmt(ast, keyword, *condition, body),
+        }
+    }
+
+    fn visit_block_stmt(&mut self, ast: &Ast, statements: &[Stmt]) -> T;
+    fn visit_expression_stmt(&mut self, ast: &Ast, expr: ExprId) -> T;
+    fn visit_function_stmt(&mut self, ast: &Ast, declaration: &Rc<FunctionDecl>) -> T;
+    fn visit_if_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> T;
+    fn visit_print_stmt(&mut self, ast: &Ast, expr: ExprId) -> T;
+    fn visit_return_stmt(&mut self, ast: &Ast, keyword: &Token, value: Option<ExprId>) -> T;
+    fn visit_var_stmt(&mut self, ast: &Ast, name: &Token, initializer: Option<ExprId>) -> T;
+    fn visit_while_stmt(&mut self, ast: &Ast, keyword: &Token, condition: ExprId, body: &Stmt)
+        -> T;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{visit_id_mut, walk_expr_mut, MutVisitor};
+    use crate::ast_printer::AstPrinter;
+    use crate::expr::{Ast, Expr, LiteralType};
+    use crate::parser::Parser;
+    use crate::printer::Printer;
+    use crate::scanner::Scanner;
+
+    struct Doubler {}
+
+    impl MutVisitor for Doubler {
+        fn visit_expr_mut(&mut self, ast: &mut Ast, expr: &mut Expr) {
+            if let Expr::Literal(LiteralType::Number(n)) = expr {
+                *n *= 2.0;
+            }
+            walk_expr_mut(self, ast, expr);
+        }
+    }
+
+    #[test]
+    fn it_rewrites_nodes_in_place() {
+        let mut scanner = Scanner::new("1 + -(2 * 3)");
+        let tokens = scanner.scan_tokens().clone();
+        let mut ast = Ast::new();
+        let expr = Parser::new(tokens, &mut ast).parse_expression().unwrap();
+
+        visit_id_mut(&mut Doubler {}, &mut ast, expr);
+
+        assert_eq!(AstPrinter {}.print(&ast, expr), "(+ 2 (- (group (* 4 6))))");
+    }
 }